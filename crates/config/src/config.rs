@@ -1,8 +1,10 @@
 use crate::configs::network::P2pConfig;
 use crate::configs::node::NodeConfig;
+use crate::configs::processor::ProcessorConfig;
 use crate::configs::rpc::JsonRpcServerConfig;
 use crate::configs::storage::RocksConfig;
 use crate::configs::tracing::TracingConfig;
+use crate::configs::vm::VmConfig;
 
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -21,6 +23,10 @@ const CONFIG_FILE: &str = "ramd.toml";
 pub struct RamdConfig {
     /// Configuration for RAM node
     pub node: NodeConfig,
+    /// Configuration for message processor
+    pub processor: ProcessorConfig,
+    /// Configuration for live object wasm execution
+    pub vm: VmConfig,
     /// Configuration for rocksdb storage
     pub rocks: RocksConfig,
     /// Configuration for jsonrpc server
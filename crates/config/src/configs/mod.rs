@@ -1,5 +1,7 @@
 pub mod network;
 pub mod node;
+pub mod processor;
 pub mod rpc;
 pub mod storage;
 pub mod tracing;
+pub mod vm;
@@ -1,33 +1,62 @@
 // use libp2p::{multiaddr::Protocol, Multiaddr};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, time::Duration};
 
+/// Encoding of sent gossip payloads, payloads of peers are decoded whatever their encoding
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WireEncoding {
+    #[default]
+    Bincode,
+    /// Human readable, meant for debugging
+    Json,
+}
+
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default)]
 pub struct P2pConfig {
     pub boot_nodes: Option<Vec<String>>,
+    /// Payloads of at least this many bytes are zstd compressed, `None` disables compression
+    pub compression_threshold: Option<usize>,
     pub config_path: PathBuf,
+    pub encoding: WireEncoding,
     pub idle_connection_timeout_secs: u64,
+    /// Malformed gossip payloads a peer may send before it's disconnected
+    pub max_invalid_messages: u32,
     pub max_peers_limit: usize,
     pub network_key: Option<PathBuf>,
     pub port: u16,
+    /// Retries of a broadcast that found no peers on its topic
+    pub publish_max_retries: u32,
+    /// Delay before the first broadcast retry, doubled with every further retry
+    pub publish_retry_backoff_ms: u64,
 }
 
 impl P2pConfig {
     pub fn idle_connection_timeout(&self) -> Duration {
         Duration::from_secs(self.idle_connection_timeout_secs)
     }
+
+    pub fn publish_retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.publish_retry_backoff_ms)
+    }
 }
 
 impl Default for P2pConfig {
     fn default() -> Self {
         Self {
             boot_nodes: None,
+            compression_threshold: Some(1024),
             config_path: PathBuf::new(),
+            encoding: WireEncoding::default(),
             idle_connection_timeout_secs: 60,
+            max_invalid_messages: 3,
             max_peers_limit: 10,
             network_key: None,
             port: 1211,
+            publish_max_retries: 5,
+            publish_retry_backoff_ms: 500,
         }
     }
 }
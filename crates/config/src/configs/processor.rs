@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default)]
+pub struct ProcessorConfig {
+    /// Whether a failing message discards the whole batch instead of only its own writes
+    pub atomic_batches: bool,
+    /// Maximum number of messages waiting in the pool for their predecessors
+    pub pool_max_size: usize,
+    /// Seconds until a message waiting in the pool is evicted
+    pub pool_stale_timeout_secs: u64,
+    /// Whether the state of all live objects is rebuilt from stored messages on start
+    pub replay_on_start: bool,
+    /// Number of threads applying messages of different live objects concurrently,
+    /// 0 uses one thread per core
+    pub workers: usize,
+    /// Number of automatic retries of a message that failed with a transient error
+    pub max_retries: u32,
+    /// Seconds until the first automatic retry, doubled with every further retry
+    pub retry_backoff_secs: u64,
+}
+
+impl ProcessorConfig {
+    pub fn pool_stale_timeout(&self) -> Duration {
+        Duration::from_secs(self.pool_stale_timeout_secs)
+    }
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            atomic_batches: false,
+            pool_max_size: 10_000,
+            pool_stale_timeout_secs: 600,
+            replay_on_start: false,
+            workers: 0,
+            max_retries: 3,
+            retry_backoff_secs: 10,
+        }
+    }
+}
@@ -1,12 +1,19 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(default)]
 pub struct JsonRpcServerConfig {
     pub port: u16,
+    /// Whether RPC callers may have messages signed with the node account, otherwise only
+    /// messages signed by clients are accepted
+    pub allow_node_signing: bool,
 }
 
 impl Default for JsonRpcServerConfig {
     fn default() -> Self {
-        Self { port: 1319 }
+        Self {
+            port: 1319,
+            allow_node_signing: false,
+        }
     }
 }
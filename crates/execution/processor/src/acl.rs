@@ -0,0 +1,151 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ramd_db::{
+    keys::{live_object_acl_key, live_object_acl_prefix},
+    storage::Storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::action::LiveObjectId;
+use crate::error::ProcessorError;
+
+/// Role of an identity on a live object, every role includes the permissions of lower roles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// May query the live object
+    Reader,
+    /// May execute methods changing the live object state
+    Writer,
+    /// May upgrade the live object and grant or revoke roles
+    Owner,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Reader => write!(f, "reader"),
+            Role::Writer => write!(f, "writer"),
+            Role::Owner => write!(f, "owner"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "writer" => Ok(Role::Writer),
+            "owner" => Ok(Role::Owner),
+            _ => Err(eyre::eyre!("Unknown role `{}`", s)),
+        }
+    }
+}
+
+/// Returns the role granted to the identity, `None` if it has no access
+pub(crate) fn read_role<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    identity: &[u8],
+) -> eyre::Result<Option<Role>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    match storage.get_opt(live_object_acl_key(live_object_id, identity))? {
+        Some(role) => Ok(Some(bincode::deserialize(&role)?)),
+        None => Ok(None),
+    }
+}
+
+/// Fails unless the identity was granted at least the role
+pub(crate) fn require_role<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    identity: &[u8],
+    required: Role,
+) -> eyre::Result<()>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    match read_role(storage, live_object_id, identity)? {
+        Some(role) if role >= required => Ok(()),
+        _ => Err(ProcessorError::Unauthorized(hex::encode(identity)).into()),
+    }
+}
+
+/// Replaces the role of the identity, a live object always keeps at least one owner
+pub(crate) fn grant_role<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    identity: &[u8],
+    role: Role,
+) -> eyre::Result<()>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    if role != Role::Owner {
+        ensure_not_last_owner(storage, live_object_id, identity)?;
+    }
+
+    storage.set(
+        live_object_acl_key(live_object_id, identity),
+        bincode::serialize(&role)?,
+    )
+}
+
+/// Removes the role of the identity, a live object always keeps at least one owner
+pub(crate) fn revoke_role<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    identity: &[u8],
+) -> eyre::Result<()>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    ensure_not_last_owner(storage, live_object_id, identity)?;
+
+    storage.delete(live_object_acl_key(live_object_id, identity))
+}
+
+fn ensure_not_last_owner<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    identity: &[u8],
+) -> eyre::Result<()>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    if read_role(storage, live_object_id, identity)? != Some(Role::Owner) {
+        return Ok(());
+    }
+
+    let owners = read_acl(storage, live_object_id)?
+        .into_iter()
+        .filter(|(_, role)| *role == Role::Owner)
+        .count();
+
+    if owners == 1 {
+        return Err(ProcessorError::LastOwner(hex::encode(live_object_id)).into());
+    }
+
+    Ok(())
+}
+
+/// Returns every identity with access to the live object together with its role
+pub(crate) fn read_acl<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+) -> eyre::Result<Vec<(Vec<u8>, Role)>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    let prefix = live_object_acl_prefix(live_object_id);
+
+    storage
+        .iter_prefix(prefix.clone())?
+        .into_iter()
+        .map(|(key, role)| Ok((key[prefix.len()..].to_vec(), bincode::deserialize(&role)?)))
+        .collect()
+}
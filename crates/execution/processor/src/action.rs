@@ -1,57 +1,313 @@
 use std::sync::Arc;
 
-use ramd_db::storage::Storage;
-use tracing::{error, info};
+use crate::acl::{grant_role, require_role, revoke_role, Role};
+use crate::error::ProcessorError;
+use crate::message::MessageId;
+use crate::receipt::Receipt;
+use crate::version::push_version;
+use ramd_db::{
+    keys::{live_object_code_key, live_object_creator_key, live_object_state_prefix},
+    storage::Storage,
+};
+use ramd_vm::{HostEnv, Vm, VmError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info};
 
+/// Hash uniquely identifying a live object
+pub type LiveObjectId = [u8; 32];
+
+/// Method of the new code called on upgrade to migrate the existing state
+pub const MIGRATE_METHOD: &str = "migrate";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
     CreateLiveObject(CreateLiveObjectAction),
     ExecuteLiveObject(ExecuteLiveObjectAction),
+    UpgradeLiveObject(UpgradeLiveObjectAction),
+    GrantRole(GrantRoleAction),
+    RevokeRole(RevokeRoleAction),
 }
 
 impl Action {
-    pub(crate) fn perform<S>(&self, cache: Arc<S>) -> eyre::Result<()>
+    /// ID of the live object this action targets
+    pub fn live_object_id(&self) -> LiveObjectId {
+        match self {
+            Action::CreateLiveObject(action) => action.live_object_id(),
+            Action::ExecuteLiveObject(action) => action.live_object_id,
+            Action::UpgradeLiveObject(action) => action.live_object_id,
+            Action::GrantRole(action) => action.live_object_id,
+            Action::RevokeRole(action) => action.live_object_id,
+        }
+    }
+
+    /// Applies the action on behalf of the message author, the ID of the message carrying it
+    /// seeds live object randomness. Live object calls are recorded to the receipt.
+    pub(crate) fn perform<S>(
+        &self,
+        cache: Arc<S>,
+        vm: &Vm,
+        message_id: &MessageId,
+        author: &[u8],
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()>
     where
-        S: Storage<Vec<u8>, Vec<u8>>,
+        S: Storage<Vec<u8>, Vec<u8>> + 'static,
     {
         match self {
-            Action::CreateLiveObject(action) => action.perform(cache),
-            Action::ExecuteLiveObject(action) => action.perform(cache),
+            Action::CreateLiveObject(action) => action.perform(cache, vm, message_id, author),
+            Action::ExecuteLiveObject(action) => {
+                action.perform(cache, vm, message_id, author, receipt)
+            }
+            Action::UpgradeLiveObject(action) => {
+                action.perform(cache, vm, message_id, author, receipt)
+            }
+            Action::GrantRole(action) => action.perform(cache, author),
+            Action::RevokeRole(action) => action.perform(cache, author),
         }
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CreateLiveObjectAction {
     pub wasm_bytes: Vec<u8>,
+    /// Public key of the live object creator, which becomes its first owner, must be the
+    /// message author
+    pub creator: Vec<u8>,
+    /// Distinguishes live objects created by the same creator from the same wasm bytes
+    pub nonce: u64,
 }
 
 impl CreateLiveObjectAction {
-    fn perform<S>(&self, cache: Arc<S>) -> eyre::Result<()>
+    /// Computes the ID as a SHA-256 hash over wasm bytes, creator and nonce
+    pub fn live_object_id(&self) -> LiveObjectId {
+        let encoded = bincode::serialize(&(&self.wasm_bytes, &self.creator, self.nonce))
+            .expect("live object ID content is always serializable");
+
+        Sha256::digest(encoded).into()
+    }
+
+    fn perform<S>(
+        &self,
+        cache: Arc<S>,
+        vm: &Vm,
+        message_id: &MessageId,
+        author: &[u8],
+    ) -> eyre::Result<()>
     where
         S: Storage<Vec<u8>, Vec<u8>>,
     {
-        // TODO: use some cryptographic hash as a key.
-        if let Err(e) = cache.set(vec![0], self.wasm_bytes.clone()) {
+        let live_object_id = self.live_object_id();
+
+        if self.creator != author {
+            return Err(ProcessorError::Unauthorized(hex::encode(author)).into());
+        }
+
+        // messages from peers skip the upload time validation of the node
+        vm.validate(&self.wasm_bytes)
+            .map_err(ProcessorError::InvalidModule)?;
+
+        let code_key = live_object_code_key(&live_object_id);
+
+        if cache.has(code_key.clone())? {
+            return Err(ProcessorError::LiveObjectExists(hex::encode(live_object_id)).into());
+        }
+
+        if let Err(e) = cache.set(code_key, self.wasm_bytes.clone()) {
             error!(target: "ramd::processor", "Failed to set wasm bytes to cache with error `{}`", e.to_string());
             return Err(e);
         }
 
-        info!(target: "ramd::processor", "Successfully performed create action");
+        cache.set(
+            live_object_creator_key(&live_object_id),
+            self.creator.clone(),
+        )?;
+        grant_role(cache.as_ref(), &live_object_id, &self.creator, Role::Owner)?;
+        push_version(
+            cache.as_ref(),
+            &live_object_id,
+            &self.wasm_bytes,
+            message_id,
+        )?;
+
+        info!(target: "ramd::processor", "Successfully performed create action for live object `{}`", hex::encode(live_object_id));
         Ok(())
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExecuteLiveObjectAction {
-    pub live_object_id: [u8; 32],
+    pub live_object_id: LiveObjectId,
     pub method: String,
     pub args: Vec<u8>,
 }
 
 impl ExecuteLiveObjectAction {
-    fn perform<S>(&self, _cache: Arc<S>) -> eyre::Result<()>
+    /// Calls the method of the stored module, state writes of the guest go to the cache.
+    /// The author must be at least a writer.
+    fn perform<S>(
+        &self,
+        cache: Arc<S>,
+        vm: &Vm,
+        message_id: &MessageId,
+        author: &[u8],
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()>
+    where
+        S: Storage<Vec<u8>, Vec<u8>> + 'static,
+    {
+        let Some(wasm_bytes) = cache.get_opt(live_object_code_key(&self.live_object_id))? else {
+            return Err(
+                ProcessorError::LiveObjectNotFound(hex::encode(self.live_object_id)).into(),
+            );
+        };
+
+        require_role(cache.as_ref(), &self.live_object_id, author, Role::Writer)?;
+
+        let env = HostEnv::new(cache, live_object_state_prefix(&self.live_object_id))
+            .with_random_seed(*message_id);
+
+        let result = match call(vm, &wasm_bytes, &self.method, &self.args, env, receipt) {
+            Ok(result) => result,
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to execute method `{}` with error `{}`", self.method, e.to_string());
+                return Err(ProcessorError::from(e).into());
+            }
+        };
+
+        debug!(target: "ramd::processor", "Method `{}` returned {} bytes", self.method, result.len());
+        receipt.return_bytes = result;
+
+        info!(target: "ramd::processor", "Successfully performed execute action for live object `{}`", hex::encode(self.live_object_id));
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeLiveObjectAction {
+    pub live_object_id: LiveObjectId,
+    pub wasm_bytes: Vec<u8>,
+    /// Args of the `migrate` method of the new code, the method isn't called if `None`
+    pub migrate_args: Option<Vec<u8>>,
+}
+
+impl UpgradeLiveObjectAction {
+    /// Replaces the code of the live object and migrates its state with the new code.
+    /// The author must be an owner.
+    fn perform<S>(
+        &self,
+        cache: Arc<S>,
+        vm: &Vm,
+        message_id: &MessageId,
+        author: &[u8],
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()>
+    where
+        S: Storage<Vec<u8>, Vec<u8>> + 'static,
+    {
+        let live_object_id = hex::encode(self.live_object_id);
+
+        if !cache.has(live_object_code_key(&self.live_object_id))? {
+            return Err(ProcessorError::LiveObjectNotFound(live_object_id).into());
+        }
+        require_role(cache.as_ref(), &self.live_object_id, author, Role::Owner)?;
+
+        vm.validate(&self.wasm_bytes)
+            .map_err(ProcessorError::InvalidModule)?;
+
+        if let Some(args) = &self.migrate_args {
+            let env = HostEnv::new(
+                cache.clone(),
+                live_object_state_prefix(&self.live_object_id),
+            )
+            .with_random_seed(*message_id);
+
+            if let Err(e) = call(vm, &self.wasm_bytes, MIGRATE_METHOD, args, env, receipt) {
+                error!(target: "ramd::processor", "Failed to migrate state of live object `{}` with error `{}`", live_object_id, e.to_string());
+                return Err(ProcessorError::from(e).into());
+            }
+        }
+
+        cache.set(
+            live_object_code_key(&self.live_object_id),
+            self.wasm_bytes.clone(),
+        )?;
+        let version = push_version(
+            cache.as_ref(),
+            &self.live_object_id,
+            &self.wasm_bytes,
+            message_id,
+        )?;
+
+        info!(target: "ramd::processor", "Successfully upgraded live object `{}` to version {}", live_object_id, version);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrantRoleAction {
+    pub live_object_id: LiveObjectId,
+    /// Public key of the account receiving the role
+    pub grantee: Vec<u8>,
+    /// Replaces the role the grantee had before
+    pub role: Role,
+}
+
+impl GrantRoleAction {
+    /// The author must be an owner
+    fn perform<S>(&self, cache: Arc<S>, author: &[u8]) -> eyre::Result<()>
     where
         S: Storage<Vec<u8>, Vec<u8>>,
     {
-        info!(target: "ramd::processor", "Successfully performed execute action");
+        require_role(cache.as_ref(), &self.live_object_id, author, Role::Owner)?;
+        grant_role(
+            cache.as_ref(),
+            &self.live_object_id,
+            &self.grantee,
+            self.role,
+        )?;
+
+        info!(target: "ramd::processor", "Granted role {} on live object `{}` to `{}`", self.role, hex::encode(self.live_object_id), hex::encode(&self.grantee));
         Ok(())
     }
 }
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokeRoleAction {
+    pub live_object_id: LiveObjectId,
+    /// Public key of the account losing its role
+    pub grantee: Vec<u8>,
+}
+
+impl RevokeRoleAction {
+    /// The author must be an owner
+    fn perform<S>(&self, cache: Arc<S>, author: &[u8]) -> eyre::Result<()>
+    where
+        S: Storage<Vec<u8>, Vec<u8>>,
+    {
+        require_role(cache.as_ref(), &self.live_object_id, author, Role::Owner)?;
+        revoke_role(cache.as_ref(), &self.live_object_id, &self.grantee)?;
+
+        info!(target: "ramd::processor", "Revoked role on live object `{}` from `{}`", hex::encode(self.live_object_id), hex::encode(&self.grantee));
+        Ok(())
+    }
+}
+
+/// Calls the live object method, fuel used is recorded to the receipt even if the call fails
+fn call(
+    vm: &Vm,
+    wasm_bytes: &[u8],
+    method: &str,
+    args: &[u8],
+    env: HostEnv,
+    receipt: &mut Receipt,
+) -> Result<Vec<u8>, VmError> {
+    let module = vm.load(wasm_bytes)?;
+    let mut executor = vm.instantiate(&module, env)?;
+
+    let result = executor.call(method, args);
+    receipt.fuel_used += executor.fuel_used();
+    receipt.events.extend(executor.take_events());
+
+    result
+}
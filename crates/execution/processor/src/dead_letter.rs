@@ -0,0 +1,168 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ramd_db::{
+    keys::{dead_letter_key, RAMD_DEAD_LETTER_PREFIX},
+    storage::Storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ProcessorError;
+use crate::message::{Message, MessageId};
+
+/// Message quarantined after it failed to be processed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub message: Message,
+    /// Error of the latest failure
+    pub error: String,
+    /// Unix time in seconds of the latest failure
+    pub failed_at: u64,
+    /// Number of times processing was retried
+    pub retries: u32,
+    /// Whether the failure may go away without changes, only those are retried automatically
+    pub transient: bool,
+}
+
+impl DeadLetter {
+    /// Whether the retry policy allows an automatic retry, the backoff doubles with every retry
+    pub fn is_due(&self, now: u64, max_retries: u32, backoff_secs: u64) -> bool {
+        self.is_retriable(max_retries) && now >= self.retry_at(backoff_secs)
+    }
+
+    /// Whether the message is retried automatically at some point
+    pub fn is_retriable(&self, max_retries: u32) -> bool {
+        self.transient && self.retries < max_retries
+    }
+
+    /// Unix time in seconds once the backoff of the latest failure passes
+    pub fn retry_at(&self, backoff_secs: u64) -> u64 {
+        let backoff = backoff_secs.saturating_mul(1u64 << self.retries.min(32));
+
+        self.failed_at.saturating_add(backoff)
+    }
+}
+
+/// Current unix time in seconds
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Whether the error may go away on a retry, e.g. a storage failure
+pub(crate) fn is_transient(e: &eyre::Report) -> bool {
+    e.downcast_ref::<ProcessorError>()
+        .is_some_and(ProcessorError::is_transient)
+}
+
+pub(crate) fn read_dead_letter<S>(
+    storage: &S,
+    message_id: &MessageId,
+) -> eyre::Result<Option<DeadLetter>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    match storage.get_opt(dead_letter_key(message_id))? {
+        Some(dead_letter) => Ok(Some(bincode::deserialize(&dead_letter)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns all quarantined messages, ordered by message ID
+pub(crate) fn read_dead_letters<S>(storage: &S) -> eyre::Result<Vec<DeadLetter>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    storage
+        .iter_prefix(Vec::from(RAMD_DEAD_LETTER_PREFIX))?
+        .into_iter()
+        .map(|(_, dead_letter)| Ok(bincode::deserialize(&dead_letter)?))
+        .collect()
+}
+
+/// Quarantines the message and returns its dead letter, a message failing again keeps
+/// counting its retries
+pub(crate) fn quarantine<S>(
+    storage: &S,
+    message: &Message,
+    error: String,
+    transient: bool,
+) -> eyre::Result<DeadLetter>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    let retries =
+        read_dead_letter(storage, &message.id)?.map_or(0, |dead_letter| dead_letter.retries + 1);
+
+    let dead_letter = DeadLetter {
+        message: message.clone(),
+        error,
+        failed_at: now(),
+        retries,
+        transient,
+    };
+
+    storage.set(
+        dead_letter_key(&message.id),
+        bincode::serialize(&dead_letter)?,
+    )?;
+
+    Ok(dead_letter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::message;
+
+    fn dead_letter(retries: u32, transient: bool) -> DeadLetter {
+        DeadLetter {
+            message: message([1; 32], &[], 0),
+            error: "failed".to_owned(),
+            failed_at: 100,
+            retries,
+            transient,
+        }
+    }
+
+    #[test]
+    fn is_due_once_the_backoff_passes() {
+        let dead_letter = dead_letter(0, true);
+
+        assert!(!dead_letter.is_due(109, 3, 10));
+        assert!(dead_letter.is_due(110, 3, 10));
+    }
+
+    #[test]
+    fn backoff_doubles_with_every_retry() {
+        let dead_letter = dead_letter(2, true);
+
+        assert_eq!(dead_letter.retry_at(10), 140);
+        assert!(!dead_letter.is_due(139, 3, 10));
+        assert!(dead_letter.is_due(140, 3, 10));
+    }
+
+    #[test]
+    fn is_never_due_once_retries_run_out() {
+        let dead_letter = dead_letter(3, true);
+
+        assert!(!dead_letter.is_retriable(3));
+        assert!(!dead_letter.is_due(u64::MAX, 3, 10));
+    }
+
+    #[test]
+    fn permanent_failures_are_never_due() {
+        let dead_letter = dead_letter(0, false);
+
+        assert!(!dead_letter.is_retriable(3));
+        assert!(!dead_letter.is_due(u64::MAX, 3, 10));
+    }
+
+    #[test]
+    fn backoff_saturates() {
+        let dead_letter = dead_letter(40, true);
+
+        assert_eq!(dead_letter.retry_at(u64::MAX), u64::MAX);
+        assert!(dead_letter.is_due(u64::MAX, 50, u64::MAX));
+    }
+}
@@ -0,0 +1,45 @@
+use ramd_vm::{HostError, VmError};
+use thiserror::Error;
+
+/// Errors surfaced while applying actions to live objects
+#[derive(Debug, Error)]
+pub enum ProcessorError {
+    #[error("Live object `{0}` not found")]
+    LiveObjectNotFound(String),
+    #[error("Live object `{0}` already exists")]
+    LiveObjectExists(String),
+    #[error("The last owner of live object `{0}` can't lose its role")]
+    LastOwner(String),
+    #[error("`{0}` is not allowed to perform this action")]
+    Unauthorized(String),
+    #[error("Invalid message `{0}`: {1}")]
+    InvalidMessage(String, String),
+    #[error("Storage access failed: {0}")]
+    Storage(String),
+    #[error("Invalid live object module: {0}")]
+    InvalidModule(#[source] VmError),
+    #[error("Live object execution failed: {0}")]
+    Vm(#[from] VmError),
+    #[error("Dead letter `{0}` not found")]
+    DeadLetterNotFound(String),
+}
+
+impl ProcessorError {
+    /// Whether the failure may go away on a retry without any change to the message
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ProcessorError::Storage(_) | ProcessorError::Vm(VmError::Host(HostError::Storage(_)))
+        )
+    }
+}
+
+/// Wraps errors of the apply path which aren't processor errors, every deterministic failure
+/// is typed, so those come from storage access
+pub(crate) fn storage_error(e: eyre::Report) -> eyre::Report {
+    if e.downcast_ref::<ProcessorError>().is_some() {
+        return e;
+    }
+
+    ProcessorError::Storage(e.to_string()).into()
+}
@@ -1,7 +1,30 @@
+mod acl;
 mod action;
+mod dead_letter;
+mod error;
 mod message;
+mod pool;
 mod processor;
+mod query;
+mod receipt;
+mod replay;
+mod resolution;
+#[cfg(test)]
+mod testing;
+mod version;
 
-pub use crate::action::{Action, CreateLiveObjectAction, ExecuteLiveObjectAction};
-pub use crate::message::Message;
+pub use crate::acl::Role;
+pub use crate::action::{
+    Action, CreateLiveObjectAction, ExecuteLiveObjectAction, GrantRoleAction, LiveObjectId,
+    RevokeRoleAction, UpgradeLiveObjectAction, MIGRATE_METHOD,
+};
+pub use crate::dead_letter::DeadLetter;
+pub use crate::error::ProcessorError;
+pub use crate::message::{Message, MessageId};
+pub use crate::pool::{MessagePool, Released};
 pub use crate::processor::Processor;
+pub use crate::query::Query;
+pub use crate::receipt::Receipt;
+pub use crate::replay::{ReplayProgress, ReplayReport, StateHash};
+pub use crate::resolution::RESOLVE_METHOD;
+pub use crate::version::LiveObjectVersion;
@@ -1,20 +1,209 @@
 use std::sync::Arc;
 
+use crate::receipt::Receipt;
 use crate::Action;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
 use ramd_db::storage::Storage;
+use ramd_vm::Vm;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+/// Content hash uniquely identifying a message
+pub type MessageId = [u8; 32];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Message {
-    // TODO: add ID and dependencies.
-    // pub id: some_cryptographic_hash,
-    // pub predecessors: Vec<some_cryptographic_hash>,
+    /// Hash of the canonical encoding of predecessors, action and author
+    pub id: MessageId,
+    /// IDs of messages that causally precede this one, sorted and deduplicated
+    pub predecessors: Vec<MessageId>,
     pub action: Action,
+    /// Ed25519 public key of the account which created the message
+    pub author: Vec<u8>,
+    /// Ed25519 signature of the author over the message ID
+    pub signature: Vec<u8>,
 }
 
 impl Message {
-    pub(crate) fn process<S>(&self, cache: Arc<S>) -> eyre::Result<()>
+    /// Creates a message on top of given predecessors, computes its ID and signs it
+    pub fn new(action: Action, mut predecessors: Vec<MessageId>, signer: &SigningKey) -> Self {
+        predecessors.sort_unstable();
+        predecessors.dedup();
+
+        let author = signer.verifying_key().to_bytes().to_vec();
+        let id = Self::compute_id(&predecessors, &action, &author);
+        let signature = signer.sign(&id).to_bytes().to_vec();
+
+        Self {
+            id,
+            predecessors,
+            action,
+            author,
+            signature,
+        }
+    }
+
+    /// Computes the ID as a SHA-256 hash over the canonical (bincode) encoding of the content
+    pub fn compute_id(predecessors: &[MessageId], action: &Action, author: &[u8]) -> MessageId {
+        let encoded = bincode::serialize(&(predecessors, action, author))
+            .expect("message content is always serializable");
+
+        Sha256::digest(encoded).into()
+    }
+
+    /// Checks that the ID matches the content and that predecessors are in canonical form
+    pub fn has_valid_id(&self) -> bool {
+        let is_canonical = self.predecessors.windows(2).all(|pair| pair[0] < pair[1]);
+
+        is_canonical && self.id == Self::compute_id(&self.predecessors, &self.action, &self.author)
+    }
+
+    /// Checks that the signature over the ID was made by the author
+    pub fn has_valid_signature(&self) -> bool {
+        verify_signature(&self.author, &self.id, &self.signature)
+    }
+
+    /// Hex encoded message ID, mainly used for logging
+    pub fn id_hex(&self) -> String {
+        hex::encode(self.id)
+    }
+
+    pub(crate) fn process<S>(
+        &self,
+        cache: Arc<S>,
+        vm: &Vm,
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()>
     where
-        S: Storage<Vec<u8>, Vec<u8>>,
+        S: Storage<Vec<u8>, Vec<u8>> + 'static,
     {
-        self.action.perform(cache)
+        self.action
+            .perform(cache, vm, &self.id, &self.author, receipt)
+    }
+}
+
+/// Checks that the ed25519 signature over the digest was made by the public key
+pub(crate) fn verify_signature(public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> bool {
+    let Ok(public_key) = <[u8; 32]>::try_from(public_key) else {
+        return false;
+    };
+    let Ok(public_key) = VerifyingKey::from_bytes(&public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+
+    public_key.verify_strict(digest, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::message;
+
+    const LIVE_OBJECT: [u8; 32] = [1; 32];
+
+    #[test]
+    fn id_depends_only_on_the_content() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[], 1);
+        let signer = SigningKey::from_bytes(&[1; 32]);
+
+        let message = Message::new(a.action.clone(), vec![b.id, a.id, b.id], &signer);
+        let same = Message::new(a.action.clone(), vec![a.id, b.id], &signer);
+
+        let mut predecessors = vec![a.id, b.id];
+        predecessors.sort_unstable();
+        assert_eq!(message.predecessors, predecessors);
+        assert_eq!(message.id, same.id);
+        assert!(message.has_valid_id());
+    }
+
+    #[test]
+    fn id_is_stable() {
+        let message = message(LIVE_OBJECT, &[], 0);
+
+        // a different ID means the canonical encoding changed and peers would disagree
+        assert_eq!(
+            message.id_hex(),
+            "e9273fd8ad543ec0df693c1a859b08472aefb9897b4d1ba41c50792786b235ee"
+        );
+    }
+
+    #[test]
+    fn detects_tampered_content() {
+        let root = message(LIVE_OBJECT, &[], 0);
+        let message = message(LIVE_OBJECT, &[&root], 1);
+
+        let mut tampered = message.clone();
+        tampered.action = root.action.clone();
+        assert!(!tampered.has_valid_id());
+
+        let mut tampered = message.clone();
+        tampered.predecessors.clear();
+        assert!(!tampered.has_valid_id());
+
+        let mut tampered = message.clone();
+        tampered.author = SigningKey::from_bytes(&[2; 32])
+            .verifying_key()
+            .to_bytes()
+            .to_vec();
+        assert!(!tampered.has_valid_id());
+
+        let mut tampered = message.clone();
+        tampered.id = root.id;
+        assert!(!tampered.has_valid_id());
+    }
+
+    #[test]
+    fn rejects_non_canonical_predecessors() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[], 1);
+
+        let mut message = message(LIVE_OBJECT, &[&a, &b], 2);
+        message.predecessors.reverse();
+        message.id = Message::compute_id(&message.predecessors, &message.action, &message.author);
+
+        assert!(!message.has_valid_id());
+    }
+
+    #[test]
+    fn verifies_the_author_signature() {
+        let message = message(LIVE_OBJECT, &[], 0);
+        assert!(message.has_valid_signature());
+
+        let other = SigningKey::from_bytes(&[2; 32]);
+
+        // signed by someone else than the author
+        let mut forged = message.clone();
+        forged.signature = other.sign(&message.id).to_bytes().to_vec();
+        assert!(!forged.has_valid_signature());
+
+        // claims another author, whose signature doesn't match
+        let mut forged = message.clone();
+        forged.author = other.verifying_key().to_bytes().to_vec();
+        assert!(!forged.has_valid_signature());
+
+        // signature over another message
+        let mut forged = message.clone();
+        forged.signature = SigningKey::from_bytes(&[1; 32])
+            .sign(&[0; 32])
+            .to_bytes()
+            .to_vec();
+        assert!(!forged.has_valid_signature());
+    }
+
+    #[test]
+    fn rejects_malformed_signatures_and_keys() {
+        let message = message(LIVE_OBJECT, &[], 0);
+
+        let mut malformed = message.clone();
+        malformed.signature.truncate(10);
+        assert!(!malformed.has_valid_signature());
+
+        let mut malformed = message.clone();
+        malformed.author = vec![1; 31];
+        assert!(!malformed.has_valid_signature());
     }
 }
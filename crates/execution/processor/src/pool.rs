@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::message::{Message, MessageId};
+use crate::LiveObjectId;
+use ramd_config::configs::processor::ProcessorConfig;
+
+struct PoolEntry {
+    message: Message,
+    inserted_at: Instant,
+}
+
+/// Messages leaving the pool in a single round
+pub struct Released {
+    /// Messages whose predecessors are applied, in the order they must be applied
+    pub ready: Vec<Message>,
+    /// Messages which can never be applied, ordered by ID
+    pub rejected: Vec<Message>,
+}
+
+/// Holds messages until all of their causal predecessors have been applied
+pub struct MessagePool {
+    entries: HashMap<MessageId, PoolEntry>,
+    max_size: usize,
+    stale_timeout: Duration,
+}
+
+impl MessagePool {
+    pub fn new(config: &ProcessorConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_size: config.pool_max_size,
+            stale_timeout: config.pool_stale_timeout(),
+        }
+    }
+
+    /// Adds message to the pool, returns `false` if it is already pooled
+    pub fn insert(&mut self, message: Message) -> eyre::Result<bool> {
+        if self.entries.contains_key(&message.id) {
+            return Ok(false);
+        }
+
+        if self.entries.len() >= self.max_size {
+            return Err(eyre::eyre!("Message pool is full"));
+        }
+
+        self.entries.insert(
+            message.id,
+            PoolEntry {
+                message,
+                inserted_at: Instant::now(),
+            },
+        );
+
+        Ok(true)
+    }
+
+    pub fn contains(&self, id: &MessageId) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns predecessors of pooled messages that are neither applied nor pooled
+    pub fn pending_dependencies<F>(&self, is_applied: F) -> BTreeSet<MessageId>
+    where
+        F: Fn(&MessageId) -> bool,
+    {
+        self.entries
+            .values()
+            .flat_map(|entry| entry.message.predecessors.iter())
+            .filter(|id| !self.entries.contains_key(*id) && !is_applied(id))
+            .copied()
+            .collect()
+    }
+
+    /// Removes and returns every message whose predecessors are applied or released before it,
+    /// `applied_live_object` returns the live object of an applied message.
+    ///
+    /// Messages are ordered topologically, ties are broken by the smallest message ID,
+    /// so every node releases the same set of messages in the same order.
+    /// Messages depending on a message of another live object, directly or through pooled
+    /// predecessors, can never be applied and are rejected.
+    pub fn release_ready<F>(&mut self, applied_live_object: F) -> Released
+    where
+        F: Fn(&MessageId) -> Option<LiveObjectId>,
+    {
+        // number of predecessors each pooled message is still waiting for
+        let mut waiting_for: HashMap<MessageId, usize> = HashMap::new();
+        // pooled predecessor -> pooled messages depending on it
+        let mut dependents: BTreeMap<MessageId, Vec<MessageId>> = BTreeMap::new();
+        let mut ready = BTreeSet::new();
+        let mut rejected = BTreeSet::new();
+
+        for (id, entry) in &self.entries {
+            let live_object_id = entry.message.action.live_object_id();
+            let mut count = 0;
+            let mut is_missing = false;
+            let mut is_foreign = false;
+
+            for predecessor in &entry.message.predecessors {
+                let predecessor_live_object = match self.entries.get(predecessor) {
+                    Some(pooled) => {
+                        dependents.entry(*predecessor).or_default().push(*id);
+                        count += 1;
+                        pooled.message.action.live_object_id()
+                    }
+                    None => match applied_live_object(predecessor) {
+                        Some(applied) => applied,
+                        None => {
+                            is_missing = true;
+                            continue;
+                        }
+                    },
+                };
+
+                is_foreign |= predecessor_live_object != live_object_id;
+            }
+
+            if is_foreign {
+                rejected.insert(*id);
+            } else if is_missing {
+                // missing predecessor, can never be released in this round
+                waiting_for.insert(*id, usize::MAX);
+            } else if count == 0 {
+                ready.insert(*id);
+            } else {
+                waiting_for.insert(*id, count);
+            }
+        }
+
+        // dependents of a rejected message can't be applied either
+        let mut queue: Vec<MessageId> = rejected.iter().copied().collect();
+        while let Some(id) = queue.pop() {
+            for dependent in dependents.get(&id).into_iter().flatten() {
+                if rejected.insert(*dependent) {
+                    waiting_for.remove(dependent);
+                    queue.push(*dependent);
+                }
+            }
+        }
+
+        let mut released = Vec::new();
+        while let Some(id) = ready.pop_first() {
+            for dependent in dependents.remove(&id).unwrap_or_default() {
+                if let Some(count) = waiting_for.get_mut(&dependent) {
+                    if *count == usize::MAX {
+                        continue;
+                    }
+
+                    *count -= 1;
+                    if *count == 0 {
+                        waiting_for.remove(&dependent);
+                        ready.insert(dependent);
+                    }
+                }
+            }
+
+            if let Some(entry) = self.entries.remove(&id) {
+                released.push(entry.message);
+            }
+        }
+
+        Released {
+            ready: released,
+            rejected: rejected
+                .iter()
+                .filter_map(|id| self.entries.remove(id))
+                .map(|entry| entry.message)
+                .collect(),
+        }
+    }
+
+    /// Removes and returns messages that have been waiting longer than the configured timeout
+    pub fn evict_stale(&mut self) -> Vec<Message> {
+        let stale_timeout = self.stale_timeout;
+        let stale: Vec<MessageId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.inserted_at.elapsed() > stale_timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut evicted: Vec<Message> = stale
+            .iter()
+            .filter_map(|id| self.entries.remove(id))
+            .map(|entry| entry.message)
+            .collect();
+
+        evicted.sort_unstable_by_key(|message| message.id);
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ids, message, sorted_ids};
+
+    const LIVE_OBJECT: LiveObjectId = [1; 32];
+    const OTHER_LIVE_OBJECT: LiveObjectId = [2; 32];
+
+    fn pool(messages: &[&Message]) -> MessagePool {
+        let mut pool = MessagePool::new(&ProcessorConfig::default());
+        for message in messages {
+            assert!(pool.insert((*message).clone()).unwrap());
+        }
+
+        pool
+    }
+
+    #[test]
+    fn releases_diamond_in_causal_order() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[&a], 1);
+        let c = message(LIVE_OBJECT, &[&a], 2);
+        let d = message(LIVE_OBJECT, &[&b, &c], 3);
+        let mut pool = pool(&[&d, &c, &b, &a]);
+
+        let released = pool.release_ready(|_| None);
+
+        let concurrent = sorted_ids(&[&b, &c]);
+        assert_eq!(
+            ids(&released.ready),
+            vec![a.id, concurrent[0], concurrent[1], d.id]
+        );
+        assert!(released.rejected.is_empty());
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn breaks_ties_by_id() {
+        let messages: Vec<Message> = (0..8)
+            .map(|nonce| message(LIVE_OBJECT, &[], nonce))
+            .collect();
+        let messages: Vec<&Message> = messages.iter().collect();
+        let mut pool = pool(&messages);
+
+        let released = pool.release_ready(|_| None);
+
+        assert_eq!(ids(&released.ready), sorted_ids(&messages));
+    }
+
+    #[test]
+    fn releases_messages_on_top_of_applied_ones() {
+        let applied = message(LIVE_OBJECT, &[], 0);
+        let next = message(LIVE_OBJECT, &[&applied], 1);
+        let mut pool = pool(&[&next]);
+
+        let released = pool.release_ready(|id| (*id == applied.id).then_some(LIVE_OBJECT));
+
+        assert_eq!(ids(&released.ready), vec![next.id]);
+    }
+
+    #[test]
+    fn holds_messages_with_missing_predecessors() {
+        let missing = message(LIVE_OBJECT, &[], 0);
+        let waiting = message(LIVE_OBJECT, &[&missing], 1);
+        let dependent = message(LIVE_OBJECT, &[&waiting], 2);
+        let mut pool = pool(&[&dependent, &waiting]);
+
+        let released = pool.release_ready(|_| None);
+        assert!(released.ready.is_empty());
+        assert!(released.rejected.is_empty());
+        assert_eq!(pool.len(), 2);
+        assert_eq!(
+            pool.pending_dependencies(|_| false),
+            BTreeSet::from([missing.id])
+        );
+
+        let released = pool.release_ready(|id| (*id == missing.id).then_some(LIVE_OBJECT));
+        assert_eq!(ids(&released.ready), vec![waiting.id, dependent.id]);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn rejects_messages_depending_on_another_live_object() {
+        let foreign = message(OTHER_LIVE_OBJECT, &[], 0);
+        let crossing = message(LIVE_OBJECT, &[&foreign], 1);
+        let dependent = message(LIVE_OBJECT, &[&crossing], 2);
+        let independent = message(LIVE_OBJECT, &[], 3);
+        let mut pool = pool(&[&dependent, &crossing, &foreign, &independent]);
+
+        let released = pool.release_ready(|_| None);
+
+        assert_eq!(ids(&released.ready), sorted_ids(&[&foreign, &independent]));
+        assert_eq!(
+            ids(&released.rejected),
+            sorted_ids(&[&crossing, &dependent])
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn rejects_messages_depending_on_an_applied_message_of_another_live_object() {
+        let foreign = message(OTHER_LIVE_OBJECT, &[], 0);
+        let crossing = message(LIVE_OBJECT, &[&foreign], 1);
+        let mut pool = pool(&[&crossing]);
+
+        let released = pool.release_ready(|id| (*id == foreign.id).then_some(OTHER_LIVE_OBJECT));
+
+        assert!(released.ready.is_empty());
+        assert_eq!(ids(&released.rejected), vec![crossing.id]);
+    }
+
+    #[test]
+    fn rejects_duplicates_and_overflow() {
+        let config = ProcessorConfig {
+            pool_max_size: 1,
+            ..Default::default()
+        };
+        let mut pool = MessagePool::new(&config);
+        let first = message(LIVE_OBJECT, &[], 0);
+
+        assert!(pool.insert(first.clone()).unwrap());
+        assert!(!pool.insert(first).unwrap());
+        assert!(pool.insert(message(LIVE_OBJECT, &[], 1)).is_err());
+    }
+
+    #[test]
+    fn evicts_stale_messages() {
+        let config = ProcessorConfig {
+            pool_stale_timeout_secs: 0,
+            ..Default::default()
+        };
+        let mut pool = MessagePool::new(&config);
+        let missing = message(LIVE_OBJECT, &[], 0);
+        let messages: Vec<Message> = (1..5)
+            .map(|nonce| message(LIVE_OBJECT, &[&missing], nonce))
+            .collect();
+        for message in &messages {
+            pool.insert(message.clone()).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+
+        let evicted = pool.evict_stale();
+
+        assert_eq!(
+            ids(&evicted),
+            sorted_ids(&messages.iter().collect::<Vec<_>>())
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn keeps_fresh_messages() {
+        let missing = message(LIVE_OBJECT, &[], 0);
+        let waiting = message(LIVE_OBJECT, &[&missing], 1);
+        let mut pool = pool(&[&waiting]);
+
+        assert!(pool.evict_stale().is_empty());
+        assert!(pool.contains(&waiting.id));
+    }
+}
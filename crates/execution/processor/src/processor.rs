@@ -1,38 +1,1617 @@
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::message::Message;
-use ramd_db::storage::Storage;
-use tracing::error;
+use crate::acl::{read_acl, read_role, Role};
+use crate::dead_letter::{
+    is_transient, now, quarantine, read_dead_letter, read_dead_letters, DeadLetter,
+};
+use crate::error::{storage_error, ProcessorError};
+use crate::message::{Message, MessageId};
+use crate::pool::MessagePool;
+use crate::receipt::{read_receipt, write_receipt, Receipt};
+use crate::replay::{causal_order, state_hash, ReplayProgress, ReplayReport, StateHash};
+use crate::resolution::{generations, resolve, Resolution, DROPPED};
+use crate::version::{read_versions, LiveObjectVersion};
+use crate::LiveObjectId;
+use ramd_config::configs::{processor::ProcessorConfig, vm::VmConfig};
+use ramd_db::{
+    cache::CacheStorage,
+    keys::{
+        dead_letter_key, live_object_code_key, live_object_depth_key, live_object_depth_prefix,
+        live_object_heads_key, live_object_prefix, live_object_state_prefix, message_key,
+        receipt_key, undo_key, undo_prefix, RAMD_DEAD_LETTER_PREFIX, RAMD_MESSAGE_PREFIX,
+    },
+    storage::Storage,
+};
+use ramd_vm::{HostEnv, Vm};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tracing::{debug, error, info, warn};
+
+/// Error of dead letters evicted from the pool while waiting for their predecessors
+const EVICTED: &str = "Evicted from the pool waiting for predecessors";
+
+/// Error of messages depending on messages of another live object
+const FOREIGN: &str = "Depends on a message of another live object";
+
+/// Error of messages not replayed because a predecessor failed or is missing
+const SKIPPED: &str = "Skipped by the replay, a predecessor wasn't applied";
+
+/// Error of messages concurrent with generations which can't be undone anymore
+const STALE: &str = "Concurrent with messages too far behind the latest applied ones";
+
+/// Number of the latest generations of a live object which can be undone to apply a
+/// concurrent message, older generations keep no undo log
+pub(crate) const MAX_REWIND_DEPTH: u64 = 64;
 
 pub struct Processor<S>
 where
     S: Storage<Vec<u8>, Vec<u8>>,
 {
     storage: Arc<S>,
+    pool: Mutex<MessagePool>,
+    vm: Vm,
+    /// Applies the lanes of different live objects concurrently
+    workers: ThreadPool,
+    atomic_batches: bool,
+    max_retries: u32,
+    retry_backoff_secs: u64,
+    /// Due time in unix seconds and ID of the dead letters retried automatically
+    retry_schedule: Mutex<BTreeSet<(u64, MessageId)>>,
 }
 
-impl<S> Processor<S>
+/// Outcome of applying the messages of a single live object
+struct Lane<S>
 where
     S: Storage<Vec<u8>, Vec<u8>>,
 {
-    pub fn new(storage: Arc<S>) -> Self {
-        Self { storage }
+    /// Writes of the applied messages, committed once all lanes are done
+    cache: Arc<CacheStorage<S>>,
+    applied: Vec<Message>,
+    /// Messages which weren't attempted and go back to the pool
+    pending: Vec<Message>,
+    /// Messages which failed to apply with their error and whether it is transient
+    failed: Vec<(Message, String, bool)>,
+    receipts: Vec<Receipt>,
+}
+
+impl<S> Processor<S>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
+{
+    pub fn new(config: &ProcessorConfig, vm_config: &VmConfig, storage: Arc<S>) -> Self {
+        let processor = Self {
+            vm: Vm::new(vm_config).with_artifact_storage(storage.clone()),
+            storage,
+            pool: Mutex::new(MessagePool::new(config)),
+            workers: ThreadPoolBuilder::new()
+                .num_threads(config.workers)
+                .thread_name(|index| format!("ramd-processor-{index}"))
+                .build()
+                .expect("processor worker pool can be created"),
+            atomic_batches: config.atomic_batches,
+            max_retries: config.max_retries,
+            retry_backoff_secs: config.retry_backoff_secs,
+            retry_schedule: Mutex::new(BTreeSet::new()),
+        };
+
+        // dead letters quarantined before a restart are still retried
+        match read_dead_letters(processor.storage.as_ref()) {
+            Ok(dead_letters) => dead_letters
+                .iter()
+                .for_each(|dead_letter| processor.schedule_retry(dead_letter)),
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to read dead letters with error `{}`", e.to_string());
+            }
+        }
+
+        processor
+    }
+
+    /// Checks the module can be stored as a live object, see [`Vm::validate`]
+    pub fn validate_module(&self, wasm_bytes: &[u8]) -> Result<(), ProcessorError> {
+        self.vm
+            .validate(wasm_bytes)
+            .map_err(ProcessorError::InvalidModule)
+    }
+
+    /// Adds messages to the pool and applies every message whose predecessors are applied.
+    ///
+    /// Messages are partitioned by live object into lanes applied concurrently by the workers,
+    /// messages within a lane keep their causal order. Each message is applied within its own
+    /// savepoint so a failure only discards its writes. With atomic batches enabled, a failure
+    /// discards the writes of the whole batch instead.
+    /// Failed messages are quarantined as dead letters, those failing with a transient error
+    /// are retried with the next batch once their backoff passes.
+    /// Returns the stored receipts of the processed messages, failed ones included.
+    pub fn process_messages(&self, messages: &[Message]) -> Vec<Receipt> {
+        let mut pool = self.pool.lock().expect("message pool lock is poisoned");
+
+        let retried = self.due_dead_letters();
+
+        for message in messages.iter().chain(&retried) {
+            if !message.has_valid_id() {
+                error!(target: "ramd::processor", "Message ID `{}` doesn't match its content", message.id_hex());
+                continue;
+            }
+
+            if !message.has_valid_signature() {
+                error!(target: "ramd::processor", "Message `{}` has an invalid signature", message.id_hex());
+                continue;
+            }
+
+            if self.is_applied(&message.id) {
+                debug!(target: "ramd::processor", "Message `{}` is already applied", message.id_hex());
+                continue;
+            }
+
+            if let Err(e) = pool.insert(message.clone()) {
+                error!(target: "ramd::processor", "Failed to add message `{}` to the pool with error `{}`", message.id_hex(), e.to_string());
+            }
+        }
+
+        for message in pool.evict_stale() {
+            warn!(target: "ramd::processor", "Evicted stale message `{}` from the pool", message.id_hex());
+            self.store_dead_letter(self.storage.as_ref(), &message, EVICTED.to_owned(), false);
+        }
+
+        // messages of different live objects are independent, each live object gets a lane
+        let mut lanes: BTreeMap<LiveObjectId, Vec<Message>> = BTreeMap::new();
+        let released = pool.release_ready(|id| self.applied_live_object(id));
+        for message in released.rejected {
+            warn!(target: "ramd::processor", "Rejected message `{}` depending on another live object", message.id_hex());
+            self.store_dead_letter(self.storage.as_ref(), &message, FOREIGN.to_owned(), false);
+        }
+
+        for message in released.ready {
+            lanes
+                .entry(message.action.live_object_id())
+                .or_default()
+                .push(message);
+        }
+
+        let abort = AtomicBool::new(false);
+        let lanes: Vec<Lane<S>> = self.workers.install(|| {
+            lanes
+                .into_par_iter()
+                .map(|(live_object_id, messages)| {
+                    self.process_lane(live_object_id, messages, &abort)
+                })
+                .collect()
+        });
+
+        let mut receipts = Vec::new();
+
+        if self.atomic_batches && abort.load(Ordering::Acquire) {
+            // whole batch was discarded, valid and not yet applied messages get another chance
+            for lane in lanes {
+                // the batch won't be committed, so failures go straight to storage
+                for receipt in lane.receipts.into_iter().filter(|r| !r.is_success()) {
+                    Self::store_receipt(self.storage.as_ref(), &receipt);
+                    receipts.push(receipt);
+                }
+                // messages applied before stay applied, their rewind is discarded as well
+                for (message, error, transient) in lane.failed {
+                    if !self.is_applied(&message.id) {
+                        self.store_dead_letter(self.storage.as_ref(), &message, error, transient);
+                    }
+                }
+                for message in lane.applied.into_iter().chain(lane.pending) {
+                    if !self.is_applied(&message.id) {
+                        Self::return_to_pool(&mut pool, message);
+                    }
+                }
+            }
+            return receipts;
+        }
+
+        for lane in lanes {
+            for message in lane.pending {
+                Self::return_to_pool(&mut pool, message);
+            }
+            for (message, error, transient) in lane.failed {
+                self.store_dead_letter(lane.cache.as_ref(), &message, error, transient);
+            }
+
+            if let Err(e) = lane.cache.commit() {
+                error!(target: "ramd::processor", "Failed to commit processed messages with error `{}`", e.to_string());
+
+                for message in lane.applied {
+                    Self::return_to_pool(&mut pool, message);
+                }
+                continue;
+            }
+
+            receipts.extend(lane.receipts);
+        }
+
+        receipts
+    }
+
+    /// Wipes the state of the live object, or of all live objects if `None`, and rebuilds it
+    /// by applying every stored message again in causal order. Stored messages are kept,
+    /// including the ones failing or skipped by the replay.
+    ///
+    /// Concurrent messages are ordered by the conflict resolution of the live object, so the
+    /// rebuilt state is the same on every node. Undo logs of the latest generations are rebuilt
+    /// along with the state. State hashes are compared with the ones recorded in the receipts,
+    /// which differ if the messages were originally applied in another order or the VM changed.
+    pub fn replay(
+        &self,
+        live_object_id: Option<&LiveObjectId>,
+        mut on_progress: impl FnMut(ReplayProgress),
+    ) -> eyre::Result<ReplayReport> {
+        // nothing else may be applied while the state is rebuilt
+        let _pool = self.pool.lock().expect("message pool lock is poisoned");
+
+        let mut messages: BTreeMap<LiveObjectId, Vec<Message>> = BTreeMap::new();
+        for (_, message) in self.storage.iter_prefix(Vec::from(RAMD_MESSAGE_PREFIX))? {
+            let message: Message = bincode::deserialize(&message)?;
+            let id = message.action.live_object_id();
+
+            if live_object_id.map_or(true, |live_object_id| *live_object_id == id) {
+                messages.entry(id).or_default().push(message);
+            }
+        }
+
+        let total = messages.values().map(Vec::len).sum();
+        let mut report = ReplayReport {
+            live_objects: messages.len(),
+            ..Default::default()
+        };
+        let mut processed = 0;
+
+        info!(target: "ramd::processor", "Replaying {} messages of {} live objects", total, report.live_objects);
+
+        for (live_object_id, messages) in messages {
+            let recorded: HashMap<MessageId, Option<StateHash>> = messages
+                .iter()
+                .map(|message| {
+                    let receipt = read_receipt(self.storage.as_ref(), &message.id)?;
+                    Ok((message.id, receipt.and_then(|receipt| receipt.state_hash)))
+                })
+                .collect::<eyre::Result<_>>()?;
+
+            let cache = Arc::new(CacheStorage::new(self.storage.clone()));
+
+            // only derived state goes away, stored messages are the history being replayed
+            let mut wiped: Vec<(Vec<u8>, Option<Vec<u8>>)> = self
+                .storage
+                .iter_prefix(live_object_prefix(&live_object_id))?
+                .into_iter()
+                .map(|(key, _)| (key, None))
+                .collect();
+            wiped.extend(
+                self.storage
+                    .iter_prefix(undo_prefix(&live_object_id))?
+                    .into_iter()
+                    .map(|(key, _)| (key, None)),
+            );
+            wiped.extend(
+                messages
+                    .iter()
+                    .map(|message| (receipt_key(&message.id), None)),
+            );
+            cache.write_batch(wiped)?;
+
+            let mut remaining: HashSet<MessageId> =
+                messages.iter().map(|message| message.id).collect();
+            let mut failed: HashSet<MessageId> = HashSet::new();
+
+            for (depth, generation) in generations(causal_order(messages), |_| None) {
+                let (generation, blocked): (Vec<_>, Vec<_>) =
+                    generation.into_iter().partition(|message| {
+                        !message.predecessors.iter().any(|id| failed.contains(id))
+                    });
+                cache.savepoint();
+
+                for message in blocked {
+                    remaining.remove(&message.id);
+                    processed += 1;
+
+                    let mut receipt = Receipt::new(message.id, live_object_id);
+                    receipt.fail(SKIPPED.to_owned());
+                    write_receipt(cache.as_ref(), &receipt)?;
+
+                    failed.insert(message.id);
+                    report.skipped.push(message.id);
+                    on_progress(ReplayProgress {
+                        live_object_id,
+                        processed,
+                        total,
+                    });
+                }
+
+                for resolution in resolve(&self.vm, cache.clone(), &live_object_id, generation) {
+                    let (message, dropped) = match resolution {
+                        Resolution::Apply(message) => (message, false),
+                        Resolution::Drop(message) => (message, true),
+                    };
+                    remaining.remove(&message.id);
+                    processed += 1;
+
+                    let mut receipt = Receipt::new(message.id, live_object_id);
+
+                    cache.savepoint();
+                    let applied = if dropped {
+                        self.drop_message(&message, cache.as_ref(), &mut receipt)
+                    } else {
+                        self.apply(&message, cache.clone(), &mut receipt)
+                    };
+
+                    match applied {
+                        Ok(()) => {
+                            cache.release_savepoint()?;
+                            report.replayed += 1;
+
+                            if let Some(Some(expected)) = recorded.get(&message.id) {
+                                if receipt.state_hash.as_ref() != Some(expected) {
+                                    warn!(target: "ramd::processor", "State hash of message `{}` differs from the recorded one", message.id_hex());
+                                    report.mismatched.push(message.id);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!(target: "ramd::processor", "Failed to replay message `{}` with error `{}`", message.id_hex(), e.to_string());
+                            cache.rollback_to_savepoint()?;
+
+                            receipt.fail(e.to_string());
+                            write_receipt(cache.as_ref(), &receipt)?;
+
+                            failed.insert(message.id);
+                            report.failed.push(message.id);
+                        }
+                    }
+
+                    on_progress(ReplayProgress {
+                        live_object_id,
+                        processed,
+                        total,
+                    });
+                }
+
+                Self::close_generation(cache.as_ref(), &live_object_id, depth)?;
+            }
+
+            // messages depending on a message that was never stored can't be placed in order
+            for message_id in &remaining {
+                let mut receipt = Receipt::new(*message_id, live_object_id);
+                receipt.fail(SKIPPED.to_owned());
+                write_receipt(cache.as_ref(), &receipt)?;
+            }
+            processed += remaining.len();
+            report.skipped.extend(remaining);
+
+            cache.commit()?;
+            info!(target: "ramd::processor", "Replayed live object `{}`, {} of {} messages processed", hex::encode(live_object_id), processed, total);
+        }
+
+        Ok(report)
+    }
+
+    /// Messages quarantined after they failed to be processed
+    pub fn dead_letters(&self) -> eyre::Result<Vec<DeadLetter>> {
+        read_dead_letters(self.storage.as_ref())
+    }
+
+    /// Quarantined message, `None` if the message isn't a dead letter
+    pub fn dead_letter(&self, message_id: &MessageId) -> eyre::Result<Option<DeadLetter>> {
+        read_dead_letter(self.storage.as_ref(), message_id)
+    }
+
+    /// Whether the retry policy retries the dead letter automatically at some point
+    pub fn is_retried(&self, dead_letter: &DeadLetter) -> bool {
+        dead_letter.is_retriable(self.max_retries)
+    }
+
+    /// Processes the quarantined message again regardless of the retry policy and returns its
+    /// receipt, `None` if it went back to the pool waiting for predecessors
+    pub fn retry_dead_letter(&self, message_id: &MessageId) -> eyre::Result<Option<Receipt>> {
+        let Some(dead_letter) = read_dead_letter(self.storage.as_ref(), message_id)? else {
+            return Err(ProcessorError::DeadLetterNotFound(hex::encode(message_id)).into());
+        };
+
+        if self.is_applied(message_id) {
+            // applied through another path in the meantime
+            self.storage.delete(dead_letter_key(message_id))?;
+            return self.receipt(message_id);
+        }
+
+        let receipts = self.process_messages(&[dead_letter.message]);
+
+        Ok(receipts
+            .into_iter()
+            .find(|receipt| receipt.message_id == *message_id))
+    }
+
+    /// Removes the quarantined message, or all of them if `None`, returns the number removed
+    pub fn purge_dead_letters(&self, message_id: Option<&MessageId>) -> eyre::Result<usize> {
+        let keys: Vec<_> = match message_id {
+            Some(message_id) => read_dead_letter(self.storage.as_ref(), message_id)?
+                .map(|_| dead_letter_key(message_id))
+                .into_iter()
+                .collect(),
+            None => self
+                .storage
+                .iter_prefix(Vec::from(RAMD_DEAD_LETTER_PREFIX))?
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect(),
+        };
+
+        let purged = keys.len();
+        self.storage
+            .write_batch(keys.into_iter().map(|key| (key, None)).collect())?;
+
+        info!(target: "ramd::processor", "Purged {} dead letters", purged);
+        Ok(purged)
+    }
+
+    /// Receipt of the processed message, `None` if it wasn't processed yet
+    pub fn receipt(&self, message_id: &MessageId) -> eyre::Result<Option<Receipt>> {
+        read_receipt(self.storage.as_ref(), message_id)
+    }
+
+    /// Number of messages waiting in the pool
+    pub fn pool_size(&self) -> usize {
+        self.pool
+            .lock()
+            .expect("message pool lock is poisoned")
+            .len()
+    }
+
+    /// Whether the message waits in the pool for its predecessors
+    pub fn is_pooled(&self, message_id: &MessageId) -> bool {
+        self.pool
+            .lock()
+            .expect("message pool lock is poisoned")
+            .contains(message_id)
+    }
+
+    /// Predecessors the pooled messages are waiting for which are unknown to this node
+    pub fn pending_dependencies(&self) -> BTreeSet<MessageId> {
+        self.pool
+            .lock()
+            .expect("message pool lock is poisoned")
+            .pending_dependencies(|id| self.is_applied(id))
+    }
+
+    /// Calls a method of the live object against its current state and returns the result.
+    ///
+    /// No message is created and state writes of the guest trap, so the state never changes.
+    /// The sender must be at least a reader of the live object.
+    pub fn query(
+        &self,
+        live_object_id: &LiveObjectId,
+        method: &str,
+        args: &[u8],
+        sender: &[u8],
+    ) -> Result<Vec<u8>, ProcessorError> {
+        let wasm_bytes = self
+            .storage
+            .get_opt(live_object_code_key(live_object_id))
+            .map_err(|e| ProcessorError::Storage(e.to_string()))?
+            .ok_or_else(|| ProcessorError::LiveObjectNotFound(hex::encode(live_object_id)))?;
+
+        let role = read_role(self.storage.as_ref(), live_object_id, sender)
+            .map_err(|e| ProcessorError::Storage(e.to_string()))?;
+        if role.is_none() {
+            return Err(ProcessorError::Unauthorized(hex::encode(sender)));
+        }
+
+        // writes already trap, the cache is never committed in case one slips through
+        let cache = Arc::new(CacheStorage::new(self.storage.clone()));
+        let env = HostEnv::new(cache, live_object_state_prefix(live_object_id)).read_only();
+
+        let result = self.vm.execute(&wasm_bytes, method, args, env)?;
+        debug!(target: "ramd::processor", "Query `{}` of live object `{}` returned {} bytes", method, hex::encode(live_object_id), result.len());
+
+        Ok(result)
+    }
+
+    /// Identities with access to the live object together with their roles
+    pub fn live_object_acl(
+        &self,
+        live_object_id: &LiveObjectId,
+    ) -> eyre::Result<Vec<(Vec<u8>, Role)>> {
+        read_acl(self.storage.as_ref(), live_object_id)
+    }
+
+    /// Code versions deployed to the live object, oldest first
+    pub fn live_object_versions(
+        &self,
+        live_object_id: &LiveObjectId,
+    ) -> eyre::Result<Vec<LiveObjectVersion>> {
+        read_versions(self.storage.as_ref(), live_object_id)
+    }
+
+    /// Applied message, `None` if this node hasn't applied it
+    pub fn message(&self, message_id: &MessageId) -> eyre::Result<Option<Message>> {
+        match self.storage.get_opt(message_key(message_id))? {
+            Some(message) => Ok(Some(bincode::deserialize(&message)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Latest applied messages of the live object, new messages should be created on top of them
+    pub fn live_object_heads(&self, live_object_id: &LiveObjectId) -> eyre::Result<Vec<MessageId>> {
+        Self::read_heads(self.storage.as_ref(), live_object_id)
+    }
+
+    /// Applies messages of a single live object in order, each within its own savepoint.
+    /// Messages are applied generation by generation of the DAG, concurrent messages are
+    /// ordered by the conflict resolution of the live object.
+    ///
+    /// Messages concurrent with or preceding messages applied before would change their order,
+    /// the generations from the lowest such message on are undone and applied again. Messages
+    /// which would undo more than the latest [`MAX_REWIND_DEPTH`] generations are rejected.
+    /// Messages applied before which fail to apply again leave the DAG and are quarantined,
+    /// their dependents go back to the pool.
+    /// With atomic batches enabled, the first failure discards the writes of the lane and
+    /// raises the abort flag which stops the other lanes.
+    fn process_lane(
+        &self,
+        live_object_id: LiveObjectId,
+        messages: Vec<Message>,
+        abort: &AtomicBool,
+    ) -> Lane<S> {
+        let mut lane = Lane {
+            cache: Arc::new(CacheStorage::new(self.storage.clone())),
+            applied: Vec::new(),
+            pending: Vec::new(),
+            failed: Vec::new(),
+            receipts: Vec::new(),
+        };
+
+        let mut generations = generations(messages, |id| {
+            Self::applied_depth(lane.cache.as_ref(), &live_object_id, id)
+        });
+
+        // messages applied before and applied again in the order of the whole DAG
+        let mut history: HashSet<MessageId> = HashSet::new();
+        // messages that weren't applied, their dependents go back to the pool
+        let mut failed: HashSet<MessageId> = HashSet::new();
+
+        let bounds =
+            Self::read_frontier(lane.cache.as_ref(), &live_object_id).and_then(|frontier| {
+                let horizon = Self::read_horizon(lane.cache.as_ref(), &live_object_id)?;
+                Ok(frontier.map(|frontier| (frontier, horizon.unwrap_or(frontier + 1))))
+            });
+        let bounds = match bounds {
+            Ok(bounds) => bounds,
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to read generations of live object `{}` with error `{}`", hex::encode(live_object_id), e.to_string());
+                lane.pending.extend(generations.into_values().flatten());
+                return lane;
+            }
+        };
+
+        if let Some((frontier, horizon)) = bounds {
+            let recent = generations.split_off(&horizon);
+            for message in std::mem::replace(&mut generations, recent)
+                .into_values()
+                .flatten()
+            {
+                warn!(target: "ramd::processor", "Rejected message `{}` concurrent with generations which can't be undone", message.id_hex());
+                failed.insert(message.id);
+                lane.failed.push((message, STALE.to_owned(), false));
+            }
+
+            if let Some(&depth) = generations
+                .keys()
+                .next()
+                .filter(|depth| **depth <= frontier)
+            {
+                debug!(target: "ramd::processor", "Rewinding live object `{}` to generation {} for messages concurrent to applied ones", hex::encode(live_object_id), depth);
+
+                let messages: Vec<Message> = generations.into_values().flatten().collect();
+                match Self::rewind(lane.cache.as_ref(), &live_object_id, depth) {
+                    Ok(applied) => {
+                        history.extend(applied.iter().map(|message| message.id));
+                        // applied messages come in generation order, ahead of their dependents
+                        generations = self::generations(
+                            applied.into_iter().chain(messages).collect(),
+                            |id| Self::applied_depth(lane.cache.as_ref(), &live_object_id, id),
+                        );
+                    }
+                    Err(e) => {
+                        error!(target: "ramd::processor", "Failed to rewind live object `{}` with error `{}`", hex::encode(live_object_id), e.to_string());
+                        lane.cache.rollback();
+                        lane.pending.extend(messages);
+                        return lane;
+                    }
+                }
+            }
+        }
+
+        for (depth, generation) in generations {
+            if self.atomic_batches && abort.load(Ordering::Acquire) {
+                lane.pending.extend(
+                    generation
+                        .into_iter()
+                        .filter(|message| !history.contains(&message.id)),
+                );
+                continue;
+            }
+
+            let (generation, blocked): (Vec<_>, Vec<_>) = generation
+                .into_iter()
+                .partition(|message| !message.predecessors.iter().any(|id| failed.contains(id)));
+            failed.extend(blocked.iter().map(|message| message.id));
+
+            lane.cache.savepoint();
+            for message in blocked {
+                if history.contains(&message.id) {
+                    // applied before, it waits in the pool for its predecessors again
+                    let mut receipt = Receipt::new(message.id, live_object_id);
+                    receipt.fail(SKIPPED.to_owned());
+                    Self::store_receipt(lane.cache.as_ref(), &receipt);
+                    Self::unrecord(lane.cache.as_ref(), &message);
+                }
+                lane.pending.push(message);
+            }
+
+            for resolution in resolve(&self.vm, lane.cache.clone(), &live_object_id, generation) {
+                let (message, dropped) = match resolution {
+                    Resolution::Apply(message) => (message, false),
+                    Resolution::Drop(message) => (message, true),
+                };
+                let is_history = history.contains(&message.id);
+
+                if self.atomic_batches && abort.load(Ordering::Acquire) {
+                    if !is_history {
+                        lane.pending.push(message);
+                    }
+                    continue;
+                }
+
+                let mut receipt = Receipt::new(message.id, live_object_id);
+
+                lane.cache.savepoint();
+                let applied = if dropped {
+                    self.drop_message(&message, lane.cache.as_ref(), &mut receipt)
+                } else {
+                    self.apply(&message, lane.cache.clone(), &mut receipt)
+                };
+
+                match applied {
+                    Ok(()) => {
+                        if let Err(e) = lane.cache.release_savepoint() {
+                            error!(target: "ramd::processor", "Failed to release savepoint with error `{}`", e.to_string());
+                        }
+                        if !is_history {
+                            lane.applied.push(message);
+                            lane.receipts.push(receipt);
+                        }
+                    }
+                    Err(e) => {
+                        error!(target: "ramd::processor", "Failed to process message `{}` with error `{}`", message.id_hex(), e.to_string());
+                        failed.insert(message.id);
+                        receipt.fail(e.to_string());
+
+                        if self.atomic_batches && !is_history {
+                            abort.store(true, Ordering::Release);
+                            lane.cache.rollback();
+                            lane.failed.push((message, e.to_string(), is_transient(&e)));
+                            lane.receipts.push(receipt);
+                            continue;
+                        }
+
+                        if let Err(e) = lane.cache.rollback_to_savepoint() {
+                            error!(target: "ramd::processor", "Failed to roll back savepoint with error `{}`", e.to_string());
+                        }
+
+                        Self::store_receipt(lane.cache.as_ref(), &receipt);
+                        if is_history {
+                            // its depth is undone, it's no longer part of the DAG either
+                            Self::unrecord(lane.cache.as_ref(), &message);
+                        } else {
+                            lane.receipts.push(receipt);
+                        }
+                        lane.failed.push((message, e.to_string(), is_transient(&e)));
+                    }
+                }
+            }
+
+            // the lane is discarded, so is the savepoint of the generation
+            if self.atomic_batches && abort.load(Ordering::Acquire) {
+                continue;
+            }
+
+            if let Err(e) = Self::close_generation(lane.cache.as_ref(), &live_object_id, depth) {
+                error!(target: "ramd::processor", "Failed to store undo log of live object `{}` with error `{}`", hex::encode(live_object_id), e.to_string());
+            }
+        }
+
+        lane
+    }
+
+    fn apply(
+        &self,
+        message: &Message,
+        cache: Arc<CacheStorage<S>>,
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()> {
+        message
+            .process(cache.clone(), &self.vm, receipt)
+            .map_err(storage_error)?;
+
+        // only writes of the action itself, bookkeeping is the same for every message
+        receipt.keys_written = cache.savepoint_writes();
+
+        Self::record(message, cache.as_ref(), receipt).map_err(storage_error)
+    }
+
+    /// Records a message left out by the conflict resolution without performing its action,
+    /// dependents of the message can still be applied
+    fn drop_message(
+        &self,
+        message: &Message,
+        cache: &CacheStorage<S>,
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()> {
+        debug!(target: "ramd::processor", "Message `{}` was dropped by conflict resolution", message.id_hex());
+        receipt.fail(DROPPED.to_owned());
+
+        Self::record(message, cache, receipt).map_err(storage_error)
+    }
+
+    /// Stores the message and its receipt and makes the message a head of the live object
+    fn record(
+        message: &Message,
+        cache: &CacheStorage<S>,
+        receipt: &mut Receipt,
+    ) -> eyre::Result<()> {
+        // a retried message leaves the dead letters once it's applied
+        Storage::<Vec<u8>, Vec<u8>>::delete(cache, dead_letter_key(&message.id))?;
+
+        // store applied message so that dependents can be released and it can be served to peers
+        cache.set(message_key(&message.id), bincode::serialize(message)?)?;
+
+        let live_object_id = message.action.live_object_id();
+        let mut depth = None;
+        for predecessor in &message.predecessors {
+            depth = depth.max(Self::read_depth(cache, &live_object_id, predecessor)?);
+        }
+        cache.set(
+            live_object_depth_key(&live_object_id, &message.id),
+            bincode::serialize(&depth.map_or(0, |depth| depth + 1))?,
+        )?;
+
+        // applied message replaces its predecessors as a head of the live object
+        let mut heads = Self::read_heads(cache, &live_object_id)?;
+        heads.retain(|id| !message.predecessors.contains(id));
+        heads.push(message.id);
+        heads.sort_unstable();
+
+        cache.set(
+            live_object_heads_key(&live_object_id),
+            bincode::serialize(&heads)?,
+        )?;
+
+        receipt.state_hash = Some(state_hash(cache, &live_object_id)?);
+        write_receipt(cache, receipt)
+    }
+
+    fn read_heads<T>(storage: &T, live_object_id: &LiveObjectId) -> eyre::Result<Vec<MessageId>>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        match storage.get_opt(live_object_heads_key(live_object_id))? {
+            Some(heads) => Ok(bincode::deserialize(&heads)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Depth of the applied message in the DAG of its live object, `None` if it isn't applied
+    fn read_depth<T>(
+        storage: &T,
+        live_object_id: &LiveObjectId,
+        message_id: &MessageId,
+    ) -> eyre::Result<Option<u64>>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        match storage.get_opt(live_object_depth_key(live_object_id, message_id))? {
+            Some(depth) => Ok(Some(bincode::deserialize(&depth)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn applied_depth<T>(
+        storage: &T,
+        live_object_id: &LiveObjectId,
+        message_id: &MessageId,
+    ) -> Option<u64>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        match Self::read_depth(storage, live_object_id, message_id) {
+            Ok(depth) => depth,
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to read depth of message `{}` with error `{}`", hex::encode(message_id), e.to_string());
+                None
+            }
+        }
+    }
+
+    /// Largest depth of the messages applied to the live object, `None` if there are none
+    fn read_frontier<T>(storage: &T, live_object_id: &LiveObjectId) -> eyre::Result<Option<u64>>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        // the deepest message is always a head
+        let mut frontier = None;
+        for head in Self::read_heads(storage, live_object_id)? {
+            frontier = frontier.max(Self::read_depth(storage, live_object_id, &head)?);
+        }
+
+        Ok(frontier)
+    }
+
+    /// Lowest depth the live object can be rewound to, `None` if no undo log is kept
+    fn read_horizon<T>(storage: &T, live_object_id: &LiveObjectId) -> eyre::Result<Option<u64>>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        let prefix = undo_prefix(live_object_id);
+
+        // depths are big endian, the first undo log is the oldest one
+        let mut horizon = None;
+        storage.scan_prefix(prefix.clone(), &mut |key, _| {
+            horizon = <[u8; 8]>::try_from(&key[prefix.len()..])
+                .ok()
+                .map(u64::from_be_bytes);
+            false
+        })?;
+
+        Ok(horizon)
     }
 
-    pub fn process_messages(&self, messages: &[Message]) {
-        // TODO: use cache that wraps around storage.
-        let cache = self.storage.clone();
+    /// Releases the savepoint of the generation at the depth and stores the values its writes
+    /// to the live object replaced, so it can be undone. Undo logs older than
+    /// [`MAX_REWIND_DEPTH`] generations are removed.
+    fn close_generation(
+        cache: &CacheStorage<S>,
+        live_object_id: &LiveObjectId,
+        depth: u64,
+    ) -> eyre::Result<()> {
+        let prefix = live_object_prefix(live_object_id);
+        let undo: Vec<(Vec<u8>, Option<Vec<u8>>)> = cache
+            .savepoint_previous_values()?
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .collect();
+        cache.release_savepoint()?;
+
+        cache.set(undo_key(live_object_id, depth), bincode::serialize(&undo)?)?;
+        if let Some(expired) = depth.checked_sub(MAX_REWIND_DEPTH) {
+            Storage::<Vec<u8>, Vec<u8>>::delete(cache, undo_key(live_object_id, expired))?;
+        }
+
+        Ok(())
+    }
 
-        // TODO: add to messsage pool and then process messages.
+    /// Undoes the generations of the live object from the depth on, newest first, and returns
+    /// the messages applied in them in generation order, so they can be applied again
+    fn rewind<T>(
+        storage: &T,
+        live_object_id: &LiveObjectId,
+        depth: u64,
+    ) -> eyre::Result<Vec<Message>>
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        let from = undo_key(live_object_id, depth);
+        let mut logs = Vec::new();
+        storage.scan_prefix(undo_prefix(live_object_id), &mut |key, log| {
+            if key >= from.as_slice() {
+                logs.push((key.to_vec(), log.to_vec()));
+            }
+            true
+        })?;
 
-        for message in messages {
-            if message.process(cache.clone()).is_err() {
-                // TODO: log message ID.
-                error!(target: "ramd::processor", "Failed to process a message");
-                return;
+        // every message applied in a generation wrote its depth
+        let depth_prefix = live_object_depth_prefix(live_object_id);
+        let mut messages = Vec::new();
+        let mut undos = Vec::with_capacity(logs.len());
+        for (key, log) in logs {
+            let undo: Vec<(Vec<u8>, Option<Vec<u8>>)> = bincode::deserialize(&log)?;
+            for (written, _) in &undo {
+                if let Some(message_id) = written.strip_prefix(depth_prefix.as_slice()) {
+                    let message = storage.get(message_key(message_id))?;
+                    messages.push(bincode::deserialize(&message)?);
+                }
             }
+            undos.push((key, undo));
+        }
+
+        for (key, undo) in undos.into_iter().rev() {
+            storage.write_batch(undo)?;
+            storage.delete(key)?;
+        }
+
+        Ok(messages)
+    }
+
+    /// Removes a message applied before which failed to apply again, it's no longer applied
+    fn unrecord(cache: &CacheStorage<S>, message: &Message) {
+        if let Err(e) = Storage::<Vec<u8>, Vec<u8>>::delete(cache, message_key(&message.id)) {
+            error!(target: "ramd::processor", "Failed to remove message `{}` with error `{}`", message.id_hex(), e.to_string());
+        }
+    }
+
+    /// Dead letters the retry policy allows to retry now, their retry is scheduled when
+    /// they are quarantined so dead letters aren't read on every batch
+    fn due_dead_letters(&self) -> Vec<Message> {
+        let now = now();
+        let mut schedule = self
+            .retry_schedule
+            .lock()
+            .expect("retry schedule lock is poisoned");
+
+        let mut due = Vec::new();
+        while let Some(&(retry_at, message_id)) = schedule.first() {
+            if retry_at > now {
+                break;
+            }
+            schedule.pop_first();
+
+            // dead letters purged, applied or rescheduled in the meantime are skipped
+            match read_dead_letter(self.storage.as_ref(), &message_id) {
+                Ok(Some(dead_letter))
+                    if dead_letter.is_due(now, self.max_retries, self.retry_backoff_secs) =>
+                {
+                    debug!(target: "ramd::processor", "Retrying dead letter `{}`, attempt {}", dead_letter.message.id_hex(), dead_letter.retries + 1);
+                    due.push(dead_letter.message);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!(target: "ramd::processor", "Failed to read dead letter `{}` with error `{}`", hex::encode(message_id), e.to_string());
+                }
+            }
+        }
+
+        due
+    }
+
+    fn store_dead_letter<T>(&self, storage: &T, message: &Message, error: String, transient: bool)
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        match quarantine(storage, message, error, transient) {
+            Ok(dead_letter) => self.schedule_retry(&dead_letter),
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to quarantine message `{}` with error `{}`", message.id_hex(), e.to_string());
+            }
+        }
+    }
+
+    /// Remembers when the dead letter is due for an automatic retry, if it's retried at all
+    fn schedule_retry(&self, dead_letter: &DeadLetter) {
+        if !dead_letter.is_retriable(self.max_retries) {
+            return;
+        }
+
+        self.retry_schedule
+            .lock()
+            .expect("retry schedule lock is poisoned")
+            .insert((
+                dead_letter.retry_at(self.retry_backoff_secs),
+                dead_letter.message.id,
+            ));
+    }
+
+    fn store_receipt<T>(storage: &T, receipt: &Receipt)
+    where
+        T: Storage<Vec<u8>, Vec<u8>>,
+    {
+        if let Err(e) = write_receipt(storage, receipt) {
+            error!(target: "ramd::processor", "Failed to store receipt of message `{}` with error `{}`", hex::encode(receipt.message_id), e.to_string());
+        }
+    }
+
+    fn return_to_pool(pool: &mut MessagePool, message: Message) {
+        if let Err(e) = pool.insert(message) {
+            error!(target: "ramd::processor", "Failed to return message to the pool with error `{}`", e.to_string());
+        }
+    }
+
+    /// Live object of the applied message, `None` if it isn't applied
+    fn applied_live_object(&self, id: &MessageId) -> Option<LiveObjectId> {
+        match self.message(id) {
+            Ok(message) => message.map(|message| message.action.live_object_id()),
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to read message `{}` with error `{}`", hex::encode(id), e.to_string());
+                None
+            }
+        }
+    }
+
+    fn is_applied(&self, id: &MessageId) -> bool {
+        match self.storage.has(message_key(id)) {
+            Ok(is_applied) => is_applied,
+            Err(e) => {
+                error!(target: "ramd::processor", "Failed to read message `{}` with error `{}`", hex::encode(id), e.to_string());
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::{Action, GrantRoleAction, RevokeRoleAction, UpgradeLiveObjectAction};
+    use crate::testing::{
+        create, execute, identity, on_heads, owner, process, processor, store_module,
+    };
+    use ed25519_dalek::SigningKey;
+    use ramd_db::memory::MemoryStorage;
+    use ramd_vm::{module_hash, HostError, VmError};
+
+    #[test]
+    fn queries_never_change_the_state() {
+        let processor = processor();
+        let live_object_id = process(&processor, create(store_module(""))).live_object_id;
+        let set = execute(live_object_id, "set", b"applied");
+        process(&processor, on_heads(&processor, set, &owner()));
+
+        let sender = identity(&owner());
+        let get = || {
+            processor
+                .query(&live_object_id, "get", &[], &sender)
+                .unwrap()
+        };
+        assert_eq!(get(), b"applied");
+
+        let heads = processor.live_object_heads(&live_object_id).unwrap();
+        assert!(matches!(
+            processor.query(&live_object_id, "set", b"queried", &sender),
+            Err(ProcessorError::Vm(VmError::Host(HostError::ReadOnly)))
+        ));
+
+        assert_eq!(get(), b"applied");
+        assert_eq!(processor.live_object_heads(&live_object_id).unwrap(), heads);
+    }
+
+    /// Migration storing its arguments as the new state
+    const MIGRATE: &str = r#"
+        (func (export "migrate") (param $args i32) (result i32)
+            (call $store (local.get $args))
+            (local.get $args))
+    "#;
+
+    /// Migration which always traps
+    const FAILING_MIGRATE: &str = r#"
+        (func (export "migrate") (param i32) (result i32)
+            unreachable)
+    "#;
+
+    /// Creates a store live object holding the value and returns its ID
+    fn create_store(processor: &Processor<MemoryStorage>, value: &[u8]) -> LiveObjectId {
+        let live_object_id = process(processor, create(store_module(""))).live_object_id;
+        let set = execute(live_object_id, "set", value);
+        assert!(process(processor, on_heads(processor, set, &owner())).is_success());
+
+        live_object_id
+    }
+
+    fn upgrade(
+        live_object_id: LiveObjectId,
+        wasm_bytes: &[u8],
+        migrate_args: Option<&[u8]>,
+    ) -> Action {
+        Action::UpgradeLiveObject(UpgradeLiveObjectAction {
+            live_object_id,
+            wasm_bytes: wasm_bytes.to_vec(),
+            migrate_args: migrate_args.map(<[u8]>::to_vec),
+        })
+    }
+
+    /// Value held by the store live object, queried as its owner
+    fn stored_value(
+        processor: &Processor<MemoryStorage>,
+        live_object_id: &LiveObjectId,
+    ) -> Vec<u8> {
+        processor
+            .query(live_object_id, "get", &[], &identity(&owner()))
+            .unwrap()
+    }
+
+    #[test]
+    fn upgrades_the_code_and_migrates_the_state() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let upgraded = store_module(MIGRATE);
+
+        let upgrade = upgrade(live_object_id, &upgraded, Some(b"migrated"));
+        let receipt = process(&processor, on_heads(&processor, upgrade, &owner()));
+
+        assert!(receipt.is_success());
+        assert_eq!(stored_value(&processor, &live_object_id), b"migrated");
+
+        let versions: Vec<_> = processor
+            .live_object_versions(&live_object_id)
+            .unwrap()
+            .into_iter()
+            .map(|version| (version.version, version.module_hash))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                (0, module_hash(&store_module(""))),
+                (1, module_hash(&upgraded))
+            ]
+        );
+        assert_eq!(
+            processor.live_object_versions(&live_object_id).unwrap()[1].message_id,
+            receipt.message_id
+        );
+    }
+
+    #[test]
+    fn upgrades_without_migrating_if_no_args_are_given() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+
+        let upgrade = upgrade(live_object_id, &store_module(FAILING_MIGRATE), None);
+        assert!(process(&processor, on_heads(&processor, upgrade, &owner())).is_success());
+
+        assert_eq!(stored_value(&processor, &live_object_id), b"old");
+        assert_eq!(
+            processor
+                .live_object_versions(&live_object_id)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn failed_migration_keeps_the_previous_code_and_state() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let code = || {
+            Storage::<_, Vec<u8>>::get(
+                processor.storage.as_ref(),
+                live_object_code_key(&live_object_id),
+            )
+            .unwrap()
+        };
+        let previous = code();
+
+        let upgrade = upgrade(
+            live_object_id,
+            &store_module(FAILING_MIGRATE),
+            Some(b"migrated"),
+        );
+        let receipt = process(&processor, on_heads(&processor, upgrade, &owner()));
+
+        assert!(receipt
+            .error
+            .unwrap()
+            .starts_with("Live object execution failed"));
+        assert_eq!(code(), previous);
+        assert_eq!(stored_value(&processor, &live_object_id), b"old");
+        assert_eq!(
+            processor
+                .live_object_versions(&live_object_id)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn only_owners_upgrade_live_objects() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let stranger = SigningKey::from_bytes(&[2; 32]);
+
+        let upgrade = upgrade(live_object_id, &store_module(MIGRATE), Some(b"migrated"));
+        let receipt = process(&processor, on_heads(&processor, upgrade, &stranger));
+
+        assert_eq!(
+            receipt.error,
+            Some(ProcessorError::Unauthorized(hex::encode(identity(&stranger))).to_string())
+        );
+        assert_eq!(stored_value(&processor, &live_object_id), b"old");
+        assert_eq!(
+            processor
+                .live_object_versions(&live_object_id)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    fn grant(live_object_id: LiveObjectId, grantee: &SigningKey, role: Role) -> Action {
+        Action::GrantRole(GrantRoleAction {
+            live_object_id,
+            grantee: identity(grantee),
+            role,
+        })
+    }
+
+    fn revoke(live_object_id: LiveObjectId, grantee: &SigningKey) -> Action {
+        Action::RevokeRole(RevokeRoleAction {
+            live_object_id,
+            grantee: identity(grantee),
+        })
+    }
+
+    /// Error of the receipt of the message performing the action, `None` if it was applied
+    fn perform(
+        processor: &Processor<MemoryStorage>,
+        action: Action,
+        signer: &SigningKey,
+    ) -> Option<String> {
+        process(processor, on_heads(processor, action, signer)).error
+    }
+
+    fn unauthorized(signer: &SigningKey) -> Option<String> {
+        Some(ProcessorError::Unauthorized(hex::encode(identity(signer))).to_string())
+    }
+
+    #[test]
+    fn executes_only_for_writers_and_owners() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"owner");
+        let (reader, writer, stranger) = (
+            SigningKey::from_bytes(&[2; 32]),
+            SigningKey::from_bytes(&[3; 32]),
+            SigningKey::from_bytes(&[4; 32]),
+        );
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &reader, Role::Reader),
+                &owner()
+            ),
+            None
+        );
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &writer, Role::Writer),
+                &owner()
+            ),
+            None
+        );
+
+        let set = |value: &[u8]| execute(live_object_id, "set", value);
+        assert_eq!(
+            perform(&processor, set(b"stranger"), &stranger),
+            unauthorized(&stranger)
+        );
+        assert_eq!(
+            perform(&processor, set(b"reader"), &reader),
+            unauthorized(&reader)
+        );
+        assert_eq!(stored_value(&processor, &live_object_id), b"owner");
+
+        assert_eq!(perform(&processor, set(b"writer"), &writer), None);
+        assert_eq!(stored_value(&processor, &live_object_id), b"writer");
+
+        // readers may still query, strangers may not
+        assert_eq!(
+            processor
+                .query(&live_object_id, "get", &[], &identity(&reader))
+                .unwrap(),
+            b"writer"
+        );
+        assert!(matches!(
+            processor.query(&live_object_id, "get", &[], &identity(&stranger)),
+            Err(ProcessorError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn writers_may_not_upgrade() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let writer = SigningKey::from_bytes(&[2; 32]);
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &writer, Role::Writer),
+                &owner()
+            ),
+            None
+        );
+
+        let upgrade = upgrade(live_object_id, &store_module(MIGRATE), Some(b"migrated"));
+        assert_eq!(perform(&processor, upgrade, &writer), unauthorized(&writer));
+        assert_eq!(
+            processor
+                .live_object_versions(&live_object_id)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn owners_grant_and_revoke_roles() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let (writer, stranger) = (
+            SigningKey::from_bytes(&[2; 32]),
+            SigningKey::from_bytes(&[3; 32]),
+        );
+        let acl = || processor.live_object_acl(&live_object_id).unwrap();
+
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &writer, Role::Writer),
+                &owner()
+            ),
+            None
+        );
+        assert!(acl().contains(&(identity(&writer), Role::Writer)));
+
+        // only owners manage roles, granting a writer doesn't let it grant further
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &stranger, Role::Writer),
+                &writer
+            ),
+            unauthorized(&writer)
+        );
+        assert_eq!(
+            perform(&processor, revoke(live_object_id, &owner()), &writer),
+            unauthorized(&writer)
+        );
+
+        assert_eq!(
+            perform(&processor, revoke(live_object_id, &writer), &owner()),
+            None
+        );
+        assert_eq!(acl(), [(identity(&owner()), Role::Owner)]);
+        assert_eq!(
+            perform(&processor, execute(live_object_id, "set", b"new"), &writer),
+            unauthorized(&writer)
+        );
+    }
+
+    #[test]
+    fn keeps_the_last_owner() {
+        let processor = processor();
+        let live_object_id = create_store(&processor, b"old");
+        let other = SigningKey::from_bytes(&[2; 32]);
+        let last_owner = Some(ProcessorError::LastOwner(hex::encode(live_object_id)).to_string());
+
+        // neither revoking nor demoting the only owner is allowed
+        assert_eq!(
+            perform(&processor, revoke(live_object_id, &owner()), &owner()),
+            last_owner
+        );
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &owner(), Role::Writer),
+                &owner()
+            ),
+            last_owner
+        );
+
+        // with another owner the first one may step down
+        assert_eq!(
+            perform(
+                &processor,
+                grant(live_object_id, &other, Role::Owner),
+                &owner()
+            ),
+            None
+        );
+        assert_eq!(
+            perform(&processor, revoke(live_object_id, &owner()), &owner()),
+            None
+        );
+        assert_eq!(
+            processor.live_object_acl(&live_object_id).unwrap(),
+            [(identity(&other), Role::Owner)]
+        );
+        assert_eq!(
+            perform(&processor, revoke(live_object_id, &other), &other),
+            last_owner
+        );
+    }
+
+    #[test]
+    fn receipts_record_what_the_call_did() {
+        let processor = processor();
+        let live_object_id = process(&processor, create(store_module(""))).live_object_id;
+
+        let set = execute(live_object_id, "set", b"value");
+        let receipt = process(&processor, on_heads(&processor, set, &owner()));
+
+        assert_eq!(receipt.error, None);
+        assert!(receipt.fuel_used > 0);
+        assert_eq!(receipt.events, [b"value"]);
+        assert_eq!(receipt.return_bytes, b"value");
+        assert_eq!(
+            receipt.keys_written,
+            [[live_object_state_prefix(&live_object_id), b"value".to_vec()].concat()]
+        );
+        assert_eq!(
+            receipt.state_hash,
+            Some(state_hash(processor.storage.as_ref(), &live_object_id).unwrap())
+        );
+        assert_eq!(
+            processor.receipt(&receipt.message_id).unwrap(),
+            Some(receipt)
+        );
+    }
+
+    #[test]
+    fn receipts_record_failures_with_the_fuel_used() {
+        let processor = processor();
+        let live_object_id = process(&processor, create(store_module(""))).live_object_id;
+        let state = state_hash(processor.storage.as_ref(), &live_object_id).unwrap();
+
+        let fail = execute(live_object_id, "fail", b"value");
+        let receipt = process(&processor, on_heads(&processor, fail, &owner()));
+
+        assert!(receipt
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Live object execution failed"));
+        assert!(receipt.fuel_used > 0);
+        assert!(receipt.events.is_empty());
+        assert!(receipt.keys_written.is_empty());
+        assert!(receipt.return_bytes.is_empty());
+        assert_eq!(receipt.state_hash, None);
+        assert_eq!(
+            state_hash(processor.storage.as_ref(), &live_object_id).unwrap(),
+            state
+        );
+        assert_eq!(
+            processor.receipt(&receipt.message_id).unwrap(),
+            Some(receipt)
+        );
+    }
+
+    /// Stores its arguments unless a value is stored already
+    const ONCE: &str = r#"
+        (func (export "once") (param $args i32) (result i32)
+            (if (call $get (i32.const 0) (i32.const 5))
+                (then unreachable))
+            (call $store (local.get $args))
+            (local.get $args))
+    "#;
+
+    fn set_on(live_object_id: LiveObjectId, value: &[u8], predecessors: &[&Message]) -> Message {
+        let predecessors = predecessors.iter().map(|message| message.id).collect();
+
+        Message::new(
+            execute(live_object_id, "set", value),
+            predecessors,
+            &owner(),
+        )
+    }
+
+    #[test]
+    fn rewinds_only_the_generations_of_concurrent_messages() {
+        let processor = processor();
+        let created = create(store_module(""));
+        let live_object_id = created.action.live_object_id();
+        let first = set_on(live_object_id, b"first", &[&created]);
+        let second = set_on(live_object_id, b"second", &[&first]);
+        let third = set_on(live_object_id, b"third", &[&second]);
+        for message in [&created, &first, &second, &third] {
+            assert!(process(&processor, message.clone()).is_success());
+        }
+
+        // receipts are written again only for the messages applied again
+        let untouched = Receipt::new(second.id, live_object_id);
+        write_receipt(processor.storage.as_ref(), &untouched).unwrap();
+
+        let concurrent = set_on(live_object_id, b"concurrent", &[&second]);
+        assert!(process(&processor, concurrent.clone()).is_success());
+
+        assert_eq!(processor.receipt(&second.id).unwrap(), Some(untouched));
+        assert!(processor.receipt(&third.id).unwrap().unwrap().is_success());
+
+        let mut heads = vec![third.id, concurrent.id];
+        heads.sort_unstable();
+        assert_eq!(processor.live_object_heads(&live_object_id).unwrap(), heads);
+        let last = if concurrent.id > third.id {
+            b"concurrent".as_slice()
+        } else {
+            b"third"
+        };
+        assert_eq!(stored_value(&processor, &live_object_id), last);
+
+        // undoing the generations left the same state as rebuilding everything
+        let state = state_hash(processor.storage.as_ref(), &live_object_id).unwrap();
+        let report = processor.replay(Some(&live_object_id), |_| {}).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(
+            state_hash(processor.storage.as_ref(), &live_object_id).unwrap(),
+            state
+        );
+    }
+
+    #[test]
+    fn rejects_messages_too_far_behind() {
+        let processor = processor();
+        let created = create(store_module(""));
+        let live_object_id = created.action.live_object_id();
+        process(&processor, created.clone());
+        for value in 0..=MAX_REWIND_DEPTH {
+            let set = execute(live_object_id, "set", &value.to_le_bytes());
+            assert!(process(&processor, on_heads(&processor, set, &owner())).is_success());
         }
+        let heads = processor.live_object_heads(&live_object_id).unwrap();
+
+        let behind = set_on(live_object_id, b"behind", &[&created]);
+        let rootless = set_on(live_object_id, b"rootless", &[]);
+        processor.process_messages(&[behind.clone(), rootless.clone()]);
+
+        let mut rejected: Vec<_> = processor
+            .dead_letters()
+            .unwrap()
+            .into_iter()
+            .map(|dead_letter| (dead_letter.message.id, dead_letter.error))
+            .collect();
+        rejected.sort_unstable();
+        let mut expected = vec![
+            (behind.id, STALE.to_owned()),
+            (rootless.id, STALE.to_owned()),
+        ];
+        expected.sort_unstable();
+        assert_eq!(rejected, expected);
+
+        assert_eq!(processor.live_object_heads(&live_object_id).unwrap(), heads);
+        assert_eq!(processor.message(&behind.id).unwrap(), None);
+        assert_eq!(processor.message(&rootless.id).unwrap(), None);
+    }
+
+    #[test]
+    fn quarantines_applied_messages_failing_to_apply_again() {
+        let processor = processor();
+        let created = create(store_module(ONCE));
+        let live_object_id = created.action.live_object_id();
+        let once = |args: &[u8]| {
+            Message::new(
+                execute(live_object_id, "once", args),
+                vec![created.id],
+                &owner(),
+            )
+        };
+        let first = once(b"first");
+        let dependent = set_on(live_object_id, b"dependent", &[&first]);
+        for message in [&created, &first, &dependent] {
+            assert!(process(&processor, message.clone()).is_success());
+        }
+
+        // ordered ahead of the first message, which then finds a value stored already
+        let (nonce, concurrent) = (0..=u8::MAX)
+            .map(|nonce| (nonce, once(&[nonce])))
+            .find(|(_, message)| message.id < first.id)
+            .unwrap();
+        assert!(process(&processor, concurrent.clone()).is_success());
+
+        assert_eq!(processor.message(&first.id).unwrap(), None);
+        assert!(!processor.receipt(&first.id).unwrap().unwrap().is_success());
+        let dead_letters = processor.dead_letters().unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].message, first);
+
+        // the dependent waits for the quarantined message again
+        assert_eq!(processor.message(&dependent.id).unwrap(), None);
+        assert!(processor.is_pooled(&dependent.id));
 
-        // TODO: cache.commit();
+        assert_eq!(
+            processor.live_object_heads(&live_object_id).unwrap(),
+            [concurrent.id]
+        );
+        assert_eq!(stored_value(&processor, &live_object_id), [nonce]);
     }
 }
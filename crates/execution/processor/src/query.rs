@@ -0,0 +1,110 @@
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::message::verify_signature;
+use crate::LiveObjectId;
+
+/// Read-only call of a live object method signed by its sender, the signature proves which
+/// identity's role the call is made with
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub live_object_id: LiveObjectId,
+    pub method: String,
+    pub args: Vec<u8>,
+    /// Unix time in seconds the query was signed at, stale queries are rejected so a
+    /// signature can't be replayed later
+    pub timestamp: u64,
+    /// Ed25519 public key of the caller
+    pub sender: Vec<u8>,
+    /// Ed25519 signature of the sender over the digest of the query
+    pub signature: Vec<u8>,
+}
+
+impl Query {
+    /// Creates a query and signs it
+    pub fn new(
+        live_object_id: LiveObjectId,
+        method: String,
+        args: Vec<u8>,
+        timestamp: u64,
+        signer: &SigningKey,
+    ) -> Self {
+        let mut query = Self {
+            live_object_id,
+            method,
+            args,
+            timestamp,
+            sender: signer.verifying_key().to_bytes().to_vec(),
+            signature: Vec::new(),
+        };
+        query.signature = signer.sign(&query.digest()).to_bytes().to_vec();
+
+        query
+    }
+
+    /// SHA-256 hash over the canonical (bincode) encoding of everything but the signature
+    pub fn digest(&self) -> [u8; 32] {
+        let encoded = bincode::serialize(&(
+            &self.live_object_id,
+            &self.method,
+            &self.args,
+            self.timestamp,
+            &self.sender,
+        ))
+        .expect("query content is always serializable");
+
+        Sha256::digest(encoded).into()
+    }
+
+    /// Checks that the signature over the digest was made by the sender
+    pub fn has_valid_signature(&self) -> bool {
+        verify_signature(&self.sender, &self.digest(), &self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query() -> Query {
+        Query::new(
+            [1; 32],
+            "get".to_owned(),
+            vec![1, 2, 3],
+            1_700_000_000,
+            &SigningKey::from_bytes(&[1; 32]),
+        )
+    }
+
+    #[test]
+    fn verifies_the_sender_signature() {
+        assert!(query().has_valid_signature());
+    }
+
+    #[test]
+    fn rejects_tampered_queries() {
+        let mut tampered = query();
+        tampered.args = vec![4];
+        assert!(!tampered.has_valid_signature());
+
+        let mut tampered = query();
+        tampered.timestamp += 1;
+        assert!(!tampered.has_valid_signature());
+    }
+
+    #[test]
+    fn rejects_another_sender() {
+        let other = SigningKey::from_bytes(&[2; 32]);
+
+        // the signature doesn't match the claimed sender
+        let mut spoofed = query();
+        spoofed.sender = other.verifying_key().to_bytes().to_vec();
+        assert!(!spoofed.has_valid_signature());
+
+        // signed by someone else than the sender
+        let mut spoofed = query();
+        spoofed.signature = other.sign(&spoofed.digest()).to_bytes().to_vec();
+        assert!(!spoofed.has_valid_signature());
+    }
+}
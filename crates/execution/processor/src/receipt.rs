@@ -0,0 +1,75 @@
+use ramd_db::{keys::receipt_key, storage::Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::action::LiveObjectId;
+use crate::message::MessageId;
+use crate::replay::StateHash;
+
+/// Outcome of processing a message, stored under the message ID
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub message_id: MessageId,
+    pub live_object_id: LiveObjectId,
+    /// Reason the message wasn't applied, `None` if it was applied
+    pub error: Option<String>,
+    /// Fuel consumed by live object calls, also charged when the action failed
+    pub fuel_used: u64,
+    /// Storage keys written or deleted by the action, empty when the action failed
+    pub keys_written: Vec<Vec<u8>>,
+    /// Events emitted by the live object, in emission order
+    pub events: Vec<Vec<u8>>,
+    /// Result of the called live object method
+    pub return_bytes: Vec<u8>,
+    /// Hash of the live object state once the message was applied, replays verify against it
+    pub state_hash: Option<StateHash>,
+}
+
+impl Receipt {
+    pub fn new(message_id: MessageId, live_object_id: LiveObjectId) -> Self {
+        Self {
+            message_id,
+            live_object_id,
+            error: None,
+            fuel_used: 0,
+            keys_written: Vec::new(),
+            events: Vec::new(),
+            return_bytes: Vec::new(),
+            state_hash: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Marks the message as not applied, dropping everything the action produced
+    pub(crate) fn fail(&mut self, error: String) {
+        self.error = Some(error);
+        self.keys_written.clear();
+        self.events.clear();
+        self.return_bytes.clear();
+        self.state_hash = None;
+    }
+}
+
+/// Returns the receipt of the message, `None` if the message wasn't processed yet
+pub(crate) fn read_receipt<S>(storage: &S, message_id: &MessageId) -> eyre::Result<Option<Receipt>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    match storage.get_opt(receipt_key(message_id))? {
+        Some(receipt) => Ok(Some(bincode::deserialize(&receipt)?)),
+        None => Ok(None),
+    }
+}
+
+/// Stores the receipt, replacing the receipt of a previous attempt
+pub(crate) fn write_receipt<S>(storage: &S, receipt: &Receipt) -> eyre::Result<()>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    storage.set(
+        receipt_key(&receipt.message_id),
+        bincode::serialize(receipt)?,
+    )
+}
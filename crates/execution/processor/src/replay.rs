@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ramd_db::{keys::live_object_prefix, storage::Storage};
+use sha2::{Digest, Sha256};
+
+use crate::action::LiveObjectId;
+use crate::message::{Message, MessageId};
+
+/// Hash over everything stored for a live object, recorded in receipts
+pub type StateHash = [u8; 32];
+
+/// Progress of a running replay, reported after every message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayProgress {
+    pub live_object_id: LiveObjectId,
+    /// Messages of all replayed live objects handled so far
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Outcome of rebuilding live object state from stored messages
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub live_objects: usize,
+    /// Messages applied again
+    pub replayed: usize,
+    /// Messages which failed to apply, their dependents are skipped
+    pub failed: Vec<MessageId>,
+    /// Messages which couldn't be replayed because a predecessor is missing or failed
+    pub skipped: Vec<MessageId>,
+    /// Messages whose state hash differs from the one recorded in their receipt
+    pub mismatched: Vec<MessageId>,
+}
+
+impl ReplayReport {
+    /// Whether the rebuilt state matches the state recorded before the replay
+    pub fn is_consistent(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Orders messages so that every message follows its predecessors, concurrent messages are
+/// ordered by ID. Messages depending on a message outside of the set are left out.
+pub(crate) fn causal_order(messages: Vec<Message>) -> Vec<Message> {
+    let mut messages: HashMap<MessageId, Message> = messages
+        .into_iter()
+        .map(|message| (message.id, message))
+        .collect();
+
+    let mut dependents: HashMap<MessageId, Vec<MessageId>> = HashMap::new();
+    let mut waiting: HashMap<MessageId, usize> = HashMap::new();
+    let mut ready = BTreeSet::new();
+
+    for message in messages.values() {
+        let known = message
+            .predecessors
+            .iter()
+            .filter(|id| messages.contains_key(*id))
+            .count();
+        if known < message.predecessors.len() {
+            continue;
+        }
+
+        for predecessor in &message.predecessors {
+            dependents.entry(*predecessor).or_default().push(message.id);
+        }
+
+        if message.predecessors.is_empty() {
+            ready.insert(message.id);
+        } else {
+            waiting.insert(message.id, message.predecessors.len());
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(messages.len());
+    while let Some(id) = ready.pop_first() {
+        for dependent in dependents.remove(&id).unwrap_or_default() {
+            if let Some(count) = waiting.get_mut(&dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    waiting.remove(&dependent);
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if let Some(message) = messages.remove(&id) {
+            ordered.push(message);
+        }
+    }
+
+    ordered
+}
+
+/// Computes the hash over all keys and values stored under the live object prefix
+pub(crate) fn state_hash<S>(storage: &S, live_object_id: &LiveObjectId) -> eyre::Result<StateHash>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    let mut hasher = Sha256::new();
+    for (key, value) in storage.iter_prefix(live_object_prefix(live_object_id))? {
+        for part in [key, value] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ids, message, sorted_ids};
+
+    const LIVE_OBJECT: LiveObjectId = [1; 32];
+
+    #[test]
+    fn orders_diamond_causally() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[&a], 1);
+        let c = message(LIVE_OBJECT, &[&a], 2);
+        let d = message(LIVE_OBJECT, &[&b, &c], 3);
+
+        let ordered = causal_order(vec![d.clone(), c.clone(), b.clone(), a.clone()]);
+
+        let concurrent = sorted_ids(&[&b, &c]);
+        assert_eq!(
+            ids(&ordered),
+            vec![a.id, concurrent[0], concurrent[1], d.id]
+        );
+    }
+
+    #[test]
+    fn orders_concurrent_messages_by_id() {
+        let root = message(LIVE_OBJECT, &[], 0);
+        let branches: Vec<Message> = (1..6)
+            .map(|nonce| message(LIVE_OBJECT, &[&root], nonce))
+            .collect();
+
+        let mut messages = branches.clone();
+        messages.reverse();
+        messages.push(root.clone());
+        let ordered = causal_order(messages);
+
+        let mut expected = vec![root.id];
+        expected.extend(sorted_ids(&branches.iter().collect::<Vec<_>>()));
+        assert_eq!(ids(&ordered), expected);
+    }
+
+    #[test]
+    fn leaves_out_messages_with_missing_predecessors() {
+        let missing = message(LIVE_OBJECT, &[], 0);
+        let waiting = message(LIVE_OBJECT, &[&missing], 1);
+        let dependent = message(LIVE_OBJECT, &[&waiting], 2);
+        let root = message(LIVE_OBJECT, &[], 3);
+        let next = message(LIVE_OBJECT, &[&root], 4);
+
+        let ordered = causal_order(vec![dependent, next.clone(), waiting, root.clone()]);
+
+        assert_eq!(ids(&ordered), vec![root.id, next.id]);
+    }
+}
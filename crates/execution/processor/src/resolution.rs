@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use ramd_db::{
+    keys::{live_object_code_key, live_object_state_prefix},
+    storage::Storage,
+};
+use ramd_vm::{HostEnv, Vm};
+use tracing::warn;
+
+use crate::action::{Action, LiveObjectId};
+use crate::message::{Message, MessageId};
+
+/// Optional export of a live object ordering its concurrent messages.
+///
+/// Args are the concurrent execute messages, each encoded as `message_id | author | method |
+/// args` with every part prefixed by its `u32` LE length. The result lists `u32` LE indices of
+/// the messages in the order they are applied, messages left out are dropped.
+pub const RESOLVE_METHOD: &str = "resolve";
+
+/// Reason recorded in the receipt of a message left out by the resolver
+pub(crate) const DROPPED: &str = "Dropped by the conflict resolution of the live object";
+
+/// How a message of a generation is handled
+pub(crate) enum Resolution {
+    Apply(Message),
+    /// The message is recorded in the DAG but its action isn't performed
+    Drop(Message),
+}
+
+/// Groups topologically ordered messages of a live object by their depth in the DAG, the
+/// length of the longest path from the message creating the live object.
+///
+/// Depth is a property of the message and its ancestry, so every replica forms the same
+/// generations whichever batches the messages arrive in. Messages of a generation don't depend
+/// on each other and are ordered by ID. `applied_depth` returns depths of predecessors applied
+/// before.
+pub(crate) fn generations<F>(
+    messages: Vec<Message>,
+    applied_depth: F,
+) -> BTreeMap<u64, Vec<Message>>
+where
+    F: Fn(&MessageId) -> Option<u64>,
+{
+    let mut depths: HashMap<MessageId, u64> = HashMap::new();
+    let mut generations: BTreeMap<u64, Vec<Message>> = BTreeMap::new();
+
+    for message in messages {
+        let depth = message
+            .predecessors
+            .iter()
+            .filter_map(|id| depths.get(id).copied().or_else(|| applied_depth(id)))
+            .max()
+            .map_or(0, |depth| depth + 1);
+
+        depths.insert(message.id, depth);
+        generations.entry(depth).or_default().push(message);
+    }
+
+    for generation in generations.values_mut() {
+        generation.sort_unstable_by_key(|message| message.id);
+    }
+
+    generations
+}
+
+/// Orders a generation of concurrent messages.
+///
+/// Messages which aren't execute actions come first, in ID order, the order of administrative
+/// actions isn't up to live object code. Execute messages follow in the order returned by the
+/// `resolve` export, or in ID order if the live object doesn't export it or it fails.
+pub(crate) fn resolve<S>(
+    vm: &Vm,
+    cache: Arc<S>,
+    live_object_id: &LiveObjectId,
+    generation: Vec<Message>,
+) -> Vec<Resolution>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
+{
+    let (executions, mut others): (Vec<_>, Vec<_>) = generation
+        .into_iter()
+        .partition(|message| matches!(message.action, Action::ExecuteLiveObject(_)));
+
+    if executions.len() < 2 {
+        others.extend(executions);
+        return others.into_iter().map(Resolution::Apply).collect();
+    }
+
+    let order = match call_resolve(vm, cache, live_object_id, &executions) {
+        Ok(order) => order,
+        Err(e) => {
+            warn!(target: "ramd::processor", "Failed to resolve concurrent messages of live object `{}` with error `{}`, falling back to ID order", hex::encode(live_object_id), e.to_string());
+            None
+        }
+    };
+
+    let mut resolutions: Vec<Resolution> = others.into_iter().map(Resolution::Apply).collect();
+    match order {
+        Some(order) => {
+            let mut executions: Vec<Option<Message>> = executions.into_iter().map(Some).collect();
+            for index in order {
+                if let Some(message) = executions[index].take() {
+                    resolutions.push(Resolution::Apply(message));
+                }
+            }
+            for message in executions.into_iter().flatten() {
+                resolutions.push(Resolution::Drop(message));
+            }
+        }
+        None => resolutions.extend(executions.into_iter().map(Resolution::Apply)),
+    }
+
+    resolutions
+}
+
+/// Calls the `resolve` export, `None` if the live object doesn't export it
+fn call_resolve<S>(
+    vm: &Vm,
+    cache: Arc<S>,
+    live_object_id: &LiveObjectId,
+    executions: &[Message],
+) -> eyre::Result<Option<Vec<usize>>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
+{
+    let Some(wasm_bytes) = cache.get_opt(live_object_code_key(live_object_id))? else {
+        return Ok(None);
+    };
+
+    let module = vm.load(&wasm_bytes)?;
+    if !module
+        .exports()
+        .any(|export| export.name() == RESOLVE_METHOD)
+    {
+        return Ok(None);
+    }
+
+    // the resolver may read the state to decide, but never change it
+    let env = HostEnv::new(cache, live_object_state_prefix(live_object_id)).read_only();
+    let result = vm
+        .instantiate(&module, env)?
+        .call(RESOLVE_METHOD, &encode_executions(executions))?;
+
+    if result.len() % 4 != 0 {
+        return Err(eyre::eyre!("Resolution result isn't a list of u32 indices"));
+    }
+
+    let mut order = Vec::with_capacity(result.len() / 4);
+    for index in result.chunks_exact(4) {
+        let index = u32::from_le_bytes([index[0], index[1], index[2], index[3]]) as usize;
+        if index >= executions.len() || order.contains(&index) {
+            return Err(eyre::eyre!("Resolution result has invalid index {}", index));
+        }
+        order.push(index);
+    }
+
+    Ok(Some(order))
+}
+
+fn encode_executions(executions: &[Message]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for message in executions {
+        let Action::ExecuteLiveObject(action) = &message.action else {
+            continue;
+        };
+
+        for part in [
+            message.id.as_slice(),
+            &message.author,
+            action.method.as_bytes(),
+            &action.args,
+        ] {
+            encoded.extend_from_slice(&(part.len() as u32).to_le_bytes());
+            encoded.extend_from_slice(part);
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ids, message, sorted_ids};
+
+    const LIVE_OBJECT: LiveObjectId = [1; 32];
+
+    #[test]
+    fn groups_diamond_by_depth() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[&a], 1);
+        let c = message(LIVE_OBJECT, &[&a], 2);
+        let d = message(LIVE_OBJECT, &[&b, &c], 3);
+
+        let generations = generations(vec![a.clone(), c.clone(), b.clone(), d.clone()], |_| None);
+
+        assert_eq!(generations.len(), 3);
+        assert_eq!(ids(&generations[&0]), vec![a.id]);
+        assert_eq!(ids(&generations[&1]), sorted_ids(&[&b, &c]));
+        assert_eq!(ids(&generations[&2]), vec![d.id]);
+    }
+
+    #[test]
+    fn depth_follows_the_longest_path() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[&a], 1);
+        let c = message(LIVE_OBJECT, &[&b], 2);
+        let d = message(LIVE_OBJECT, &[&a, &c], 3);
+
+        let generations = generations(vec![a, b, c, d.clone()], |_| None);
+
+        assert_eq!(ids(&generations[&3]), vec![d.id]);
+    }
+
+    #[test]
+    fn continues_from_applied_depths() {
+        let applied = message(LIVE_OBJECT, &[], 0);
+        let next = message(LIVE_OBJECT, &[&applied], 1);
+        let concurrent = message(LIVE_OBJECT, &[], 2);
+
+        let generations = generations(vec![next.clone(), concurrent.clone()], |id| {
+            (*id == applied.id).then_some(4)
+        });
+
+        assert_eq!(ids(&generations[&0]), vec![concurrent.id]);
+        assert_eq!(ids(&generations[&5]), vec![next.id]);
+    }
+
+    #[test]
+    fn batches_form_the_same_generations() {
+        let a = message(LIVE_OBJECT, &[], 0);
+        let b = message(LIVE_OBJECT, &[&a], 1);
+        let c = message(LIVE_OBJECT, &[&a], 2);
+        let d = message(LIVE_OBJECT, &[&b], 3);
+
+        let whole = generations(vec![a.clone(), b.clone(), c.clone(), d.clone()], |_| None);
+
+        let first = generations(vec![a.clone(), b.clone()], |_| None);
+        let depth_of = |id: &MessageId| {
+            first
+                .iter()
+                .find(|(_, generation)| generation.iter().any(|message| message.id == *id))
+                .map(|(depth, _)| *depth)
+        };
+        let second = generations(vec![c.clone(), d.clone()], depth_of);
+
+        assert_eq!(ids(&whole[&1]), sorted_ids(&[&b, &c]));
+        assert_eq!(ids(&first[&1]), vec![b.id]);
+        assert_eq!(ids(&second[&1]), vec![c.id]);
+        assert_eq!(ids(&second[&2]), vec![d.id]);
+        assert_eq!(ids(&whole[&2]), vec![d.id]);
+    }
+}
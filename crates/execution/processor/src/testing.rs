@@ -0,0 +1,140 @@
+//! Helpers building messages and live objects for unit tests
+
+use std::sync::Arc;
+
+use ed25519_dalek::SigningKey;
+use ramd_config::configs::{processor::ProcessorConfig, vm::VmConfig};
+use ramd_db::memory::MemoryStorage;
+
+use crate::action::{Action, CreateLiveObjectAction, ExecuteLiveObjectAction, LiveObjectId};
+use crate::message::{Message, MessageId};
+use crate::processor::Processor;
+use crate::receipt::Receipt;
+
+/// Live object storing its arguments under a single key: `set` stores them, emits them as an
+/// event and returns them, `get` returns the stored value and `fail` traps. `$store` stores
+/// the arguments for the functions added by [`store_module`].
+const STORE: &str = r#"
+    (import "ramd" "state_get" (func $get (param i32 i32) (result i32)))
+    (import "ramd" "state_set" (func $set (param i32 i32 i32 i32)))
+    (import "ramd" "emit_event" (func $emit (param i32 i32)))
+    (memory (export "memory") 2)
+    (data (i32.const 0) "value")
+    (global $bump (mut i32) (i32.const 1024))
+    (func $allocate (export "allocate") (param $len i32) (result i32)
+        (local $slice i32)
+        (local.set $slice (global.get $bump))
+        (i32.store (local.get $slice) (i32.add (local.get $slice) (i32.const 8)))
+        (i32.store offset=4 (local.get $slice) (local.get $len))
+        (global.set $bump
+            (i32.add (global.get $bump) (i32.add (local.get $len) (i32.const 8))))
+        (local.get $slice))
+    (func (export "deallocate") (param i32))
+    (func $store (param $args i32)
+        (call $set (i32.const 0) (i32.const 5)
+            (i32.load (local.get $args)) (i32.load offset=4 (local.get $args)))
+        (call $emit (i32.load (local.get $args)) (i32.load offset=4 (local.get $args))))
+    (func (export "set") (param $args i32) (result i32)
+        (call $store (local.get $args))
+        (local.get $args))
+    (func (export "get") (param i32) (result i32)
+        (call $get (i32.const 0) (i32.const 5)))
+    (func (export "fail") (param i32) (result i32)
+        unreachable)
+"#;
+
+/// Author of the test messages, the owner of the live objects they create
+pub(crate) fn owner() -> SigningKey {
+    SigningKey::from_bytes(&[1; 32])
+}
+
+/// Public key of the signer, the identity roles are granted to
+pub(crate) fn identity(signer: &SigningKey) -> Vec<u8> {
+    signer.verifying_key().to_bytes().to_vec()
+}
+
+/// Processor over an empty in-memory storage
+pub(crate) fn processor() -> Processor<MemoryStorage> {
+    Processor::new(
+        &ProcessorConfig::default(),
+        &VmConfig::default(),
+        Arc::new(MemoryStorage::default()),
+    )
+}
+
+/// Wasm binary of the [`STORE`] live object extended with the wat functions
+pub(crate) fn store_module(functions: &str) -> Vec<u8> {
+    wat::parse_str(format!("(module {STORE} {functions})")).expect("test module is valid wat")
+}
+
+/// Message of the [`owner`] creating a live object from the wasm bytes
+pub(crate) fn create(wasm_bytes: Vec<u8>) -> Message {
+    let action = Action::CreateLiveObject(CreateLiveObjectAction {
+        wasm_bytes,
+        creator: identity(&owner()),
+        nonce: 0,
+    });
+
+    Message::new(action, vec![], &owner())
+}
+
+/// Action calling the method of the live object
+pub(crate) fn execute(live_object_id: LiveObjectId, method: &str, args: &[u8]) -> Action {
+    Action::ExecuteLiveObject(ExecuteLiveObjectAction {
+        live_object_id,
+        method: method.to_owned(),
+        args: args.to_vec(),
+    })
+}
+
+/// Message of the signer performing the action on top of the heads of its live object
+pub(crate) fn on_heads(
+    processor: &Processor<MemoryStorage>,
+    action: Action,
+    signer: &SigningKey,
+) -> Message {
+    let heads = processor
+        .live_object_heads(&action.live_object_id())
+        .unwrap();
+
+    Message::new(action, heads, signer)
+}
+
+/// Processes the message on its own and returns its receipt
+pub(crate) fn process(processor: &Processor<MemoryStorage>, message: Message) -> Receipt {
+    let message_id = message.id;
+
+    processor
+        .process_messages(&[message])
+        .into_iter()
+        .find(|receipt| receipt.message_id == message_id)
+        .expect("message is processed")
+}
+
+/// Execute message of the live object on top of the predecessors, the nonce tells otherwise
+/// identical messages apart
+pub(crate) fn message(
+    live_object_id: LiveObjectId,
+    predecessors: &[&Message],
+    nonce: u8,
+) -> Message {
+    let predecessors = predecessors.iter().map(|message| message.id).collect();
+
+    Message::new(
+        execute(live_object_id, "run", &[nonce]),
+        predecessors,
+        &owner(),
+    )
+}
+
+/// IDs of the messages, in the same order
+pub(crate) fn ids(messages: &[Message]) -> Vec<MessageId> {
+    messages.iter().map(|message| message.id).collect()
+}
+
+/// IDs of the messages, ordered by ID
+pub(crate) fn sorted_ids(messages: &[&Message]) -> Vec<MessageId> {
+    let mut ids: Vec<MessageId> = messages.iter().map(|message| message.id).collect();
+    ids.sort_unstable();
+    ids
+}
@@ -0,0 +1,64 @@
+use ramd_db::{
+    keys::{live_object_code_version_key, live_object_versions_key},
+    storage::Storage,
+};
+use ramd_vm::{module_hash, ModuleHash};
+use serde::{Deserialize, Serialize};
+
+use crate::action::LiveObjectId;
+use crate::message::MessageId;
+
+/// Code deployed to a live object, by creation or by an upgrade
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveObjectVersion {
+    /// Starts at 0 on creation and increases by one with every upgrade
+    pub version: u64,
+    pub module_hash: ModuleHash,
+    /// Message which deployed the code
+    pub message_id: MessageId,
+}
+
+/// Returns all versions of the live object, oldest first
+pub(crate) fn read_versions<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+) -> eyre::Result<Vec<LiveObjectVersion>>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    match storage.get_opt(live_object_versions_key(live_object_id))? {
+        Some(versions) => Ok(bincode::deserialize(&versions)?),
+        None => Ok(vec![]),
+    }
+}
+
+/// Records new code of the live object and returns its version
+pub(crate) fn push_version<S>(
+    storage: &S,
+    live_object_id: &LiveObjectId,
+    wasm_bytes: &[u8],
+    message_id: &MessageId,
+) -> eyre::Result<u64>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + ?Sized,
+{
+    let mut versions = read_versions(storage, live_object_id)?;
+    let version = versions.last().map_or(0, |latest| latest.version + 1);
+
+    versions.push(LiveObjectVersion {
+        version,
+        module_hash: module_hash(wasm_bytes),
+        message_id: *message_id,
+    });
+
+    storage.set(
+        live_object_code_version_key(live_object_id, version),
+        Vec::from(wasm_bytes),
+    )?;
+    storage.set(
+        live_object_versions_key(live_object_id),
+        bincode::serialize(&versions)?,
+    )?;
+
+    Ok(version)
+}
@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+/// Errors raised by the host side of the live object ABI
+#[derive(Debug, Error)]
+pub enum HostError {
+    #[error("Invalid memory slice")]
+    InvalidMemorySlice,
+    #[error("Result of {len} bytes exceeds the limit of {max_len} bytes")]
+    ResultTooLarge { len: usize, max_len: usize },
+    #[error("Host environment is not initialized with guest exports")]
+    Uninitialized,
+    #[error("Guest allocation failed: {0}")]
+    Allocation(String),
+    #[error("State writes are not allowed in a read-only call")]
+    ReadOnly,
+    #[error("Randomness is not available, the environment has no seed")]
+    RandomnessUnavailable,
+    #[error("Requested {len} random bytes, the limit is {max_len} bytes")]
+    RandomTooLarge { len: usize, max_len: usize },
+    #[error("Event of {len} bytes exceeds the limit of {max_len} bytes")]
+    EventTooLarge { len: usize, max_len: usize },
+    #[error("State storage failed: {0}")]
+    Storage(String),
+    #[error("Out of fuel")]
+    OutOfFuel,
+    #[error("Fuel accounting failed: {0}")]
+    Fuel(String),
+}
@@ -0,0 +1,341 @@
+use std::sync::Arc;
+
+use crate::error::HostError;
+use crate::memory::MemorySlice;
+use crate::random::DeterministicRng;
+use ramd_db::storage::Storage;
+use wasmer::{
+    AsStoreMut, Function, FunctionEnv, FunctionEnvMut, Global, Imports, Memory, RuntimeError,
+    TypedFunction, Value,
+};
+
+/// Name of the import module providing host functions to live objects
+pub const HOST_MODULE: &str = "ramd";
+
+/// Functions of the host module, live objects may not import anything else
+pub const HOST_FUNCTIONS: &[&str] = &[
+    "state_get",
+    "state_set",
+    "state_delete",
+    "state_iter_prefix",
+    "random_bytes",
+    "emit_event",
+];
+
+/// Maximum number of random bytes a single `random_bytes` call may request
+const MAX_RANDOM_LEN: usize = 64 * 1024;
+
+/// Maximum size of a single event emitted by `emit_event`
+const MAX_EVENT_LEN: usize = 64 * 1024;
+
+/// Fuel charged for every host call on top of the work it does
+const HOST_CALL_FUEL: u64 = 100;
+
+/// Fuel charged for every state entry a host call reads or writes
+const ENTRY_FUEL: u64 = 100;
+
+/// Globals holding the fuel of the instance, host calls draw from the fuel of the guest
+#[derive(Clone)]
+pub struct Fuel {
+    pub remaining: Global,
+    pub exhausted: Global,
+}
+
+/// State shared by host functions of a single live object instance
+pub struct HostEnv {
+    storage: Arc<dyn Storage<Vec<u8>, Vec<u8>>>,
+    /// Storage key prefix isolating the state of the live object
+    namespace: Vec<u8>,
+    memory: Option<Memory>,
+    allocate: Option<TypedFunction<u32, u32>>,
+    fuel: Option<Fuel>,
+    random: Option<DeterministicRng>,
+    /// Events emitted by the guest, in emission order
+    events: Vec<Vec<u8>>,
+    /// Whether state writes trap, used for queries which must not change state
+    read_only: bool,
+}
+
+impl HostEnv {
+    pub fn new(storage: Arc<dyn Storage<Vec<u8>, Vec<u8>>>, namespace: Vec<u8>) -> Self {
+        Self {
+            storage,
+            namespace,
+            memory: None,
+            allocate: None,
+            fuel: None,
+            random: None,
+            events: Vec::new(),
+            read_only: false,
+        }
+    }
+
+    /// Makes `state_set` and `state_delete` trap
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Enables `random_bytes`, the seed must be the same on every replica, e.g. the message ID
+    pub fn with_random_seed(mut self, seed: [u8; 32]) -> Self {
+        self.random = Some(DeterministicRng::new(seed));
+        self
+    }
+
+    /// Binds guest exports used to exchange data and charge fuel, must be called once the
+    /// module is instantiated
+    pub fn init(&mut self, memory: Memory, allocate: TypedFunction<u32, u32>, fuel: Fuel) {
+        self.memory = Some(memory);
+        self.allocate = Some(allocate);
+        self.fuel = Some(fuel);
+    }
+
+    /// Returns the events emitted so far and clears them
+    pub fn take_events(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn namespaced(&self, key: &[u8]) -> Vec<u8> {
+        [self.namespace.as_slice(), key].concat()
+    }
+}
+
+impl From<HostError> for RuntimeError {
+    fn from(e: HostError) -> Self {
+        // keeps the type, so callers can tell host failures from faults of the guest
+        RuntimeError::user(Box::new(e))
+    }
+}
+
+/// Creates imports exposing the state of the live object to the guest.
+///
+/// Keys and values are passed as `(ptr, len)` pairs pointing into guest memory. Values are
+/// returned as a pointer to a `MemorySlice` allocated through the guest `allocate` export.
+pub fn imports(store: &mut impl AsStoreMut, env: HostEnv) -> (Imports, FunctionEnv<HostEnv>) {
+    let env = FunctionEnv::new(store, env);
+
+    let imports = wasmer::imports! {
+        HOST_MODULE => {
+            "state_get" => Function::new_typed_with_env(store, &env, state_get),
+            "state_set" => Function::new_typed_with_env(store, &env, state_set),
+            "state_delete" => Function::new_typed_with_env(store, &env, state_delete),
+            "state_iter_prefix" => Function::new_typed_with_env(store, &env, state_iter_prefix),
+            "random_bytes" => Function::new_typed_with_env(store, &env, random_bytes),
+            "emit_event" => Function::new_typed_with_env(store, &env, emit_event),
+        }
+    };
+
+    (imports, env)
+}
+
+/// Returns pointer to the value, or 0 if the key doesn't exist
+fn state_get(
+    mut env: FunctionEnvMut<HostEnv>,
+    key_ptr: u32,
+    key_len: u32,
+) -> Result<u32, RuntimeError> {
+    charge(&mut env, HOST_CALL_FUEL + ENTRY_FUEL + key_len as u64)?;
+    let key = read_guest(&env, key_ptr, key_len)?;
+
+    let value = env
+        .data()
+        .storage
+        .get_opt(env.data().namespaced(&key))
+        .map_err(|e| HostError::Storage(e.to_string()))?;
+
+    match value {
+        Some(value) => {
+            charge(&mut env, value.len() as u64)?;
+            write_guest(&mut env, &value)
+        }
+        None => Ok(0),
+    }
+}
+
+fn state_set(
+    mut env: FunctionEnvMut<HostEnv>,
+    key_ptr: u32,
+    key_len: u32,
+    value_ptr: u32,
+    value_len: u32,
+) -> Result<(), RuntimeError> {
+    if env.data().read_only {
+        return Err(HostError::ReadOnly.into());
+    }
+
+    charge(
+        &mut env,
+        HOST_CALL_FUEL + ENTRY_FUEL + key_len as u64 + value_len as u64,
+    )?;
+
+    let key = read_guest(&env, key_ptr, key_len)?;
+    let value = read_guest(&env, value_ptr, value_len)?;
+
+    env.data()
+        .storage
+        .set(env.data().namespaced(&key), value)
+        .map_err(|e| HostError::Storage(e.to_string()).into())
+}
+
+fn state_delete(
+    mut env: FunctionEnvMut<HostEnv>,
+    key_ptr: u32,
+    key_len: u32,
+) -> Result<(), RuntimeError> {
+    if env.data().read_only {
+        return Err(HostError::ReadOnly.into());
+    }
+
+    charge(&mut env, HOST_CALL_FUEL + ENTRY_FUEL + key_len as u64)?;
+
+    let key = read_guest(&env, key_ptr, key_len)?;
+
+    env.data()
+        .storage
+        .delete(env.data().namespaced(&key))
+        .map_err(|e| HostError::Storage(e.to_string()).into())
+}
+
+/// Returns pointer to all entries under the prefix, each encoded as
+/// `key_len (u32 LE) | key | value_len (u32 LE) | value`, keys are relative to the namespace
+fn state_iter_prefix(
+    mut env: FunctionEnvMut<HostEnv>,
+    prefix_ptr: u32,
+    prefix_len: u32,
+) -> Result<u32, RuntimeError> {
+    charge(&mut env, HOST_CALL_FUEL + prefix_len as u64)?;
+    let prefix = read_guest(&env, prefix_ptr, prefix_len)?;
+
+    let namespace_len = env.data().namespace.len();
+    let namespaced = env.data().namespaced(&prefix);
+    let storage = env.data().storage.clone();
+
+    // entries are charged one by one as the storage is scanned, so a huge prefix runs out of
+    // fuel before the rest of it is read
+    let mut encoded = Vec::new();
+    let mut failure = None;
+    storage
+        .scan_prefix(namespaced, &mut |key, value| {
+            let key = &key[namespace_len..];
+            let encoded_entry = charge(&mut env, ENTRY_FUEL + (key.len() + value.len()) as u64)
+                .and_then(|()| {
+                    for part in [key, value] {
+                        let len =
+                            u32::try_from(part.len()).map_err(|_| HostError::InvalidMemorySlice)?;
+                        encoded.extend_from_slice(&len.to_le_bytes());
+                        encoded.extend_from_slice(part);
+                    }
+                    Ok(())
+                });
+
+            match encoded_entry {
+                Ok(()) => true,
+                Err(e) => {
+                    failure = Some(e);
+                    false
+                }
+            }
+        })
+        .map_err(|e| HostError::Storage(e.to_string()))?;
+
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+
+    write_guest(&mut env, &encoded)
+}
+
+/// Returns pointer to `len` bytes of the deterministic random stream of the environment
+fn random_bytes(mut env: FunctionEnvMut<HostEnv>, len: u32) -> Result<u32, RuntimeError> {
+    let len = len as usize;
+    if len > MAX_RANDOM_LEN {
+        return Err(HostError::RandomTooLarge {
+            len,
+            max_len: MAX_RANDOM_LEN,
+        }
+        .into());
+    }
+
+    charge(&mut env, HOST_CALL_FUEL + len as u64)?;
+    let bytes = env
+        .data_mut()
+        .random
+        .as_mut()
+        .ok_or(HostError::RandomnessUnavailable)?
+        .next_bytes(len);
+
+    write_guest(&mut env, &bytes)
+}
+
+/// Records an opaque event, events end up in the receipt of the message
+fn emit_event(mut env: FunctionEnvMut<HostEnv>, ptr: u32, len: u32) -> Result<(), RuntimeError> {
+    if len as usize > MAX_EVENT_LEN {
+        return Err(HostError::EventTooLarge {
+            len: len as usize,
+            max_len: MAX_EVENT_LEN,
+        }
+        .into());
+    }
+
+    charge(&mut env, HOST_CALL_FUEL + len as u64)?;
+    let event = read_guest(&env, ptr, len)?;
+    env.data_mut().events.push(event);
+
+    Ok(())
+}
+
+/// Draws the cost from the fuel of the guest, marking the fuel exhausted if it runs out
+fn charge(env: &mut FunctionEnvMut<HostEnv>, cost: u64) -> Result<(), HostError> {
+    let fuel = env.data().fuel.clone().ok_or(HostError::Uninitialized)?;
+
+    let remaining = fuel.remaining.get(env).i64().unwrap_or(0) as u64;
+    let (value, global) = match remaining.checked_sub(cost) {
+        Some(left) => (Value::I64(left as i64), &fuel.remaining),
+        None => (Value::I32(1), &fuel.exhausted),
+    };
+    global
+        .set(env, value)
+        .map_err(|e| HostError::Fuel(e.to_string()))?;
+
+    if remaining < cost {
+        return Err(HostError::OutOfFuel);
+    }
+
+    Ok(())
+}
+
+/// Copies `len` bytes starting at `ptr` out of guest memory
+fn read_guest(env: &FunctionEnvMut<HostEnv>, ptr: u32, len: u32) -> Result<Vec<u8>, HostError> {
+    let memory = env.data().memory.clone().ok_or(HostError::Uninitialized)?;
+    let view = memory.view(env);
+
+    if ptr as u64 + len as u64 > view.data_size() {
+        return Err(HostError::InvalidMemorySlice);
+    }
+
+    let mut data = vec![0u8; len as usize];
+    view.read(ptr as u64, &mut data)
+        .map_err(|_| HostError::InvalidMemorySlice)?;
+
+    Ok(data)
+}
+
+/// Allocates guest memory for the data, copies it there and returns pointer to its `MemorySlice`
+fn write_guest(env: &mut FunctionEnvMut<HostEnv>, data: &[u8]) -> Result<u32, RuntimeError> {
+    let memory = env.data().memory.clone().ok_or(HostError::Uninitialized)?;
+    let allocate = env
+        .data()
+        .allocate
+        .clone()
+        .ok_or(HostError::Uninitialized)?;
+
+    let len = u32::try_from(data.len()).map_err(|_| HostError::InvalidMemorySlice)?;
+    let ptr = allocate
+        .call(env, len)
+        .map_err(|e| HostError::Allocation(e.to_string()))?;
+
+    let view = memory.view(env);
+    MemorySlice::new(&view, ptr)?.write(&view, data)?;
+
+    Ok(ptr)
+}
@@ -1 +1,9 @@
+mod error;
+mod host;
+mod memory;
+mod random;
 
+pub use crate::error::HostError;
+pub use crate::host::{imports, Fuel, HostEnv, HOST_FUNCTIONS, HOST_MODULE};
+pub use crate::memory::MemorySlice;
+pub use crate::random::DeterministicRng;
@@ -0,0 +1,80 @@
+use std::mem::size_of;
+
+use crate::error::HostError;
+use wasmer::{MemoryView, WasmPtr};
+
+/// Region of guest memory exchanged between host and live object, as laid out by the guest
+pub struct MemorySlice {
+    /// A pointer to the start of this memory slice,
+    /// measured in bytes from the beginning of the WASM (guest) memory.
+    pub ptr: u32,
+    /// The number of bytes in this memory slice.
+    pub len: u32,
+}
+
+type MemorySlicePtrBytes = [u8; size_of::<MemorySlice>()];
+
+impl MemorySlice {
+    /// Read in a `MemorySlice` from the WASM (guest) memory and return it.
+    pub fn new(memory: &MemoryView, ptr: u32) -> Result<MemorySlice, HostError> {
+        let wasm_ptr = WasmPtr::<MemorySlicePtrBytes>::new(ptr);
+        let memory_slice_ptr_bytes = wasm_ptr
+            .deref(memory)
+            .read()
+            .map_err(|_| HostError::InvalidMemorySlice)?;
+        let memory_slice = MemorySlice::from_memory_slice_ptr_bytes(memory_slice_ptr_bytes);
+
+        memory_slice.validate()?;
+
+        Ok(memory_slice)
+    }
+
+    /// Write the given data to the memory slice.
+    pub fn write(self, memory: &MemoryView, data: &[u8]) -> Result<(), HostError> {
+        if data.len() > self.len as usize {
+            return Err(HostError::InvalidMemorySlice);
+        }
+
+        memory
+            .write(self.ptr as u64, data)
+            .map_err(|_| HostError::InvalidMemorySlice)
+    }
+
+    /// Read the memory slice.
+    pub fn read(self, memory: &MemoryView, max_len: usize) -> Result<Vec<u8>, HostError> {
+        if self.len as usize > max_len {
+            return Err(HostError::ResultTooLarge {
+                len: self.len as usize,
+                max_len,
+            });
+        }
+
+        let mut data = vec![0u8; self.len as usize];
+        memory
+            .read(self.ptr as u64, &mut data)
+            .map_err(|_| HostError::InvalidMemorySlice)?;
+
+        Ok(data)
+    }
+
+    /// Convert a `MemorySlicePtrBytes` to a `MemorySlice`.
+    fn from_memory_slice_ptr_bytes(bytes: MemorySlicePtrBytes) -> Self {
+        MemorySlice {
+            ptr: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            len: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        }
+    }
+
+    /// Validate the memory slice.
+    fn validate(&self) -> Result<(), HostError> {
+        if self.ptr == 0 {
+            return Err(HostError::InvalidMemorySlice);
+        }
+
+        if self.len > (u32::MAX - self.ptr) {
+            return Err(HostError::InvalidMemorySlice);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use sha2::{Digest, Sha256};
+
+/// Pseudo-random byte stream derived from a seed, every replica seeded alike yields the same bytes.
+///
+/// Blocks are computed as `SHA-256(seed | counter)` with an increasing little endian counter.
+pub struct DeterministicRng {
+    seed: [u8; 32],
+    counter: u64,
+}
+
+impl DeterministicRng {
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self { seed, counter: 0 }
+    }
+
+    /// Returns the next `len` bytes of the stream
+    pub fn next_bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            let block = Sha256::new()
+                .chain_update(self.seed)
+                .chain_update(self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+
+            let take = (len - bytes.len()).min(block.len());
+            bytes.extend_from_slice(&block[..take]);
+        }
+
+        bytes
+    }
+}
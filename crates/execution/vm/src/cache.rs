@@ -0,0 +1,268 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use lru::LruCache;
+use ramd_db::{
+    keys::{module_artifact_engine_prefix, module_artifact_key, RAMD_MODULE_ARTIFACT_PREFIX},
+    storage::Storage,
+};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use wasmer::{Engine, Module};
+
+/// Hash of the wasm bytes a module was compiled from
+pub type ModuleHash = [u8; 32];
+
+/// Compiled modules kept in memory with an LRU bound and persisted as serialized artifacts.
+///
+/// Artifacts are stored under the engine ID, which changes with anything affecting the compiled
+/// code, so artifacts of another engine or compiler version are never loaded. Each artifact is
+/// prefixed by a checksum binding it to the engine and the wasm bytes, artifacts which were
+/// corrupted or stored under another key are never deserialized.
+pub struct ModuleCache {
+    modules: Mutex<LruCache<ModuleHash, Module>>,
+    storage: Option<Arc<dyn Storage<Vec<u8>, Vec<u8>>>>,
+    engine_id: [u8; 32],
+}
+
+impl ModuleCache {
+    pub fn new(size: usize, engine_id: [u8; 32]) -> Self {
+        Self {
+            modules: Mutex::new(LruCache::new(
+                NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN),
+            )),
+            storage: None,
+            engine_id,
+        }
+    }
+
+    /// Persists artifacts to the storage and removes artifacts of other engines from it
+    pub fn with_storage(mut self, storage: Arc<dyn Storage<Vec<u8>, Vec<u8>>>) -> Self {
+        if let Err(e) = self.purge_stale(storage.as_ref()) {
+            warn!(target: "ramd::vm", "Failed to purge stale module artifacts with error `{}`", e.to_string());
+        }
+
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.modules().cap().get()
+    }
+
+    pub fn engine_id(&self) -> [u8; 32] {
+        self.engine_id
+    }
+
+    /// Returns the module from memory or storage, `None` if it was never cached
+    pub fn get(&self, engine: &Engine, module_hash: &ModuleHash) -> Option<Module> {
+        if let Some(module) = self.modules().get(module_hash) {
+            return Some(module.clone());
+        }
+
+        let storage = self.storage.as_ref()?;
+        let key = module_artifact_key(&self.engine_id, module_hash);
+        let stored = storage.get_opt(key.clone()).ok()??;
+
+        let Some(artifact) = self.verify(module_hash, &stored) else {
+            warn!(target: "ramd::vm", "Removing corrupted artifact of module `{}`", hex::encode(module_hash));
+            if let Err(e) = storage.delete(key) {
+                warn!(target: "ramd::vm", "Failed to remove artifact of module `{}` with error `{}`", hex::encode(module_hash), e.to_string());
+            }
+            return None;
+        };
+
+        // SAFETY: the checksum proves the artifact is the one `insert` serialized from a module
+        // compiled by this engine from these wasm bytes
+        match unsafe { Module::deserialize(engine, artifact) } {
+            Ok(module) => {
+                debug!(target: "ramd::vm", "Loaded module `{}` from stored artifact", hex::encode(module_hash));
+                self.modules().put(*module_hash, module.clone());
+                Some(module)
+            }
+            Err(e) => {
+                warn!(target: "ramd::vm", "Failed to deserialize module `{}` with error `{}`", hex::encode(module_hash), e.to_string());
+                None
+            }
+        }
+    }
+
+    /// Caches the compiled module, persisting its artifact if storage is attached
+    pub fn insert(&self, module_hash: ModuleHash, module: Module) {
+        if let Some(storage) = &self.storage {
+            let stored = module
+                .serialize()
+                .map_err(|e| eyre::eyre!(e))
+                .and_then(|artifact| {
+                    let checksum = self.checksum(&module_hash, &artifact);
+                    storage.set(
+                        module_artifact_key(&self.engine_id, &module_hash),
+                        [checksum.as_slice(), &artifact].concat(),
+                    )
+                });
+
+            if let Err(e) = stored {
+                warn!(target: "ramd::vm", "Failed to store artifact of module `{}` with error `{}`", hex::encode(module_hash), e.to_string());
+            }
+        }
+
+        self.modules().put(module_hash, module);
+    }
+
+    /// Hash binding the artifact to the engine and the wasm bytes it was compiled from
+    fn checksum(&self, module_hash: &ModuleHash, artifact: &[u8]) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(self.engine_id)
+            .chain_update(module_hash)
+            .chain_update(artifact)
+            .finalize()
+            .into()
+    }
+
+    /// Returns the artifact of the stored entry, `None` if its checksum doesn't match
+    fn verify<'a>(&self, module_hash: &ModuleHash, stored: &'a [u8]) -> Option<&'a [u8]> {
+        if stored.len() < 32 {
+            return None;
+        }
+
+        let (checksum, artifact) = stored.split_at(32);
+        (checksum == self.checksum(module_hash, artifact)).then_some(artifact)
+    }
+
+    fn purge_stale(&self, storage: &dyn Storage<Vec<u8>, Vec<u8>>) -> eyre::Result<()> {
+        let current = module_artifact_engine_prefix(&self.engine_id);
+
+        let stale: Vec<_> = storage
+            .iter_prefix(Vec::from(RAMD_MODULE_ARTIFACT_PREFIX))?
+            .into_iter()
+            .filter(|(key, _)| !key.starts_with(&current))
+            .map(|(key, _)| (key, None))
+            .collect();
+
+        if !stale.is_empty() {
+            debug!(target: "ramd::vm", "Removing {} stale module artifacts", stale.len());
+            storage.write_batch(stale)?;
+        }
+
+        Ok(())
+    }
+
+    fn modules(&self) -> MutexGuard<'_, LruCache<ModuleHash, Module>> {
+        self.modules.lock().expect("module cache lock is poisoned")
+    }
+}
+
+/// Computes the hash identifying a module by its wasm bytes
+pub fn module_hash(wasm_bytes: &[u8]) -> ModuleHash {
+    Sha256::digest(wasm_bytes).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ramd_db::memory::MemoryStorage;
+
+    const ENGINE: [u8; 32] = [1; 32];
+
+    /// Compiles a module whose only export is a function named after the given export
+    fn compile(engine: &Engine, export: &str) -> (ModuleHash, Module) {
+        let wasm_bytes = wat::parse_str(format!(r#"(module (func (export "{export}")))"#)).unwrap();
+        let module = Module::new(engine, &wasm_bytes).unwrap();
+
+        (module_hash(&wasm_bytes), module)
+    }
+
+    fn export(module: &Module) -> String {
+        module.exports().next().unwrap().name().to_owned()
+    }
+
+    fn stored_cache(storage: &Arc<MemoryStorage>, size: usize) -> ModuleCache {
+        ModuleCache::new(size, ENGINE).with_storage(storage.clone())
+    }
+
+    #[test]
+    fn evicts_least_recently_used_modules() {
+        let engine = Engine::default();
+        let cache = ModuleCache::new(2, ENGINE);
+        let (a, module_a) = compile(&engine, "a");
+        let (b, module_b) = compile(&engine, "b");
+        let (c, module_c) = compile(&engine, "c");
+
+        cache.insert(a, module_a);
+        cache.insert(b, module_b);
+        // `a` becomes the most recently used one
+        assert!(cache.get(&engine, &a).is_some());
+        cache.insert(c, module_c);
+
+        assert!(cache.get(&engine, &a).is_some());
+        assert!(cache.get(&engine, &b).is_none());
+        assert!(cache.get(&engine, &c).is_some());
+    }
+
+    #[test]
+    fn reloads_modules_from_storage() {
+        let engine = Engine::default();
+        let storage = Arc::new(MemoryStorage::default());
+        let (a, module_a) = compile(&engine, "a");
+        let (b, module_b) = compile(&engine, "b");
+
+        let cache = stored_cache(&storage, 1);
+        cache.insert(a, module_a);
+        cache.insert(b, module_b);
+
+        // evicted from memory, but not from storage
+        assert_eq!(export(&cache.get(&engine, &a).unwrap()), "a");
+
+        // a restarted node finds the artifacts
+        let restarted = stored_cache(&storage, 1);
+        assert_eq!(export(&restarted.get(&engine, &b).unwrap()), "b");
+        assert!(restarted.get(&engine, &[0; 32]).is_none());
+    }
+
+    #[test]
+    fn rejects_corrupted_artifacts() {
+        let engine = Engine::default();
+        let storage = Arc::new(MemoryStorage::default());
+        let (a, module_a) = compile(&engine, "a");
+        let (b, _) = compile(&engine, "b");
+        stored_cache(&storage, 1).insert(a, module_a);
+
+        let key = module_artifact_key(&ENGINE, &a);
+        let mut artifact = Storage::<_, Vec<u8>>::get(storage.as_ref(), key.clone()).unwrap();
+        let last = artifact.len() - 1;
+        artifact[last] ^= 1;
+        storage.set(key.clone(), artifact.clone()).unwrap();
+
+        assert!(stored_cache(&storage, 1).get(&engine, &a).is_none());
+        assert!(!Storage::<_, Vec<u8>>::has(storage.as_ref(), key.clone()).unwrap());
+
+        // an intact artifact stored under another module
+        artifact[last] ^= 1;
+        storage
+            .set(module_artifact_key(&ENGINE, &b), artifact)
+            .unwrap();
+        assert!(stored_cache(&storage, 1).get(&engine, &b).is_none());
+    }
+
+    #[test]
+    fn purges_artifacts_of_other_engines() {
+        let engine = Engine::default();
+        let storage = Arc::new(MemoryStorage::default());
+        let (a, module_a) = compile(&engine, "a");
+
+        ModuleCache::new(1, [2; 32])
+            .with_storage(storage.clone())
+            .insert(a, module_a.clone());
+        stored_cache(&storage, 1).insert(a, module_a);
+
+        let artifacts = |engine_id: [u8; 32]| {
+            Storage::<_, Vec<u8>>::iter_prefix(
+                storage.as_ref(),
+                module_artifact_engine_prefix(&engine_id),
+            )
+            .unwrap()
+            .len()
+        };
+        assert_eq!((artifacts(ENGINE), artifacts([2; 32])), (1, 0));
+    }
+}
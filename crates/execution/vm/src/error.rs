@@ -0,0 +1,44 @@
+use ramd_vm_runtime::HostError;
+use thiserror::Error;
+use wasmer::RuntimeError;
+
+/// Errors returned while compiling or executing live object modules
+#[derive(Debug, Error)]
+pub enum VmError {
+    #[error("Failed to compile module: {0}")]
+    Compilation(String),
+    #[error("Failed to instantiate module: {0}")]
+    Instantiation(String),
+    #[error("Bytes are not a wasm binary")]
+    NotWasm,
+    #[error("Module of {len} bytes exceeds the limit of {max_len} bytes")]
+    ModuleTooLarge { len: usize, max_len: usize },
+    #[error("Module defines {count} functions, the limit is {max_count}")]
+    TooManyFunctions { count: u32, max_count: u32 },
+    #[error("Import `{0}` is not provided by the host")]
+    ForbiddenImport(String),
+    #[error("Required export `{0}` is missing")]
+    MissingExport(String),
+    #[error("Export `{0}` has an invalid type, expected {1}")]
+    InvalidExport(String, String),
+    #[error("Method `{0}` is not exported by the module")]
+    MethodNotFound(String),
+    #[error("Invalid arguments: {0}")]
+    InvalidArgs(String),
+    #[error("Execution ran out of fuel, limit is {0}")]
+    OutOfFuel(u64),
+    #[error("Execution trapped: {0}")]
+    Trap(String),
+    #[error(transparent)]
+    Host(#[from] HostError),
+}
+
+impl From<RuntimeError> for VmError {
+    /// Errors raised by host imports keep their type, anything else is a trap of the guest
+    fn from(e: RuntimeError) -> Self {
+        match e.downcast::<HostError>() {
+            Ok(e) => VmError::Host(e),
+            Err(e) => VmError::Trap(e.to_string()),
+        }
+    }
+}
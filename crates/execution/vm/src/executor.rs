@@ -0,0 +1,736 @@
+use std::sync::Arc;
+
+use crate::cache::{module_hash, ModuleCache};
+use crate::error::VmError;
+use crate::limits::{
+    FUEL_LIMIT, MAX_MEMORY_PAGES, MAX_MODULE_FUNCTIONS, MAX_MODULE_SIZE, MAX_RESULT_LEN,
+    MAX_TABLE_ELEMENTS,
+};
+use crate::metering::{self, Metering};
+use crate::tunables::LimitingTunables;
+use ramd_config::configs::vm::VmConfig;
+use ramd_db::storage::Storage;
+use ramd_vm_runtime::{imports, HostEnv, MemorySlice, HOST_FUNCTIONS, HOST_MODULE};
+use sha2::{Digest, Sha256};
+use wasmer::sys::{BaseTunables, EngineBuilder, Features};
+use wasmer::wasmparser::{Parser, Payload};
+use wasmer::{
+    CompilerConfig, Cranelift, Engine, ExportError, ExternType, FunctionEnv, Instance, Memory,
+    Module, NativeEngineExt, Store, Type, TypedFunction,
+};
+
+/// Version of the compilation profile, bump whenever the compiled code changes without
+/// a change of wasmer version, e.g. metering or enabled features
+const PROFILE_VERSION: u32 = 2;
+
+/// Compiles live object modules and instantiates them for execution
+#[derive(Clone)]
+pub struct Vm {
+    engine: Engine,
+    modules: Arc<ModuleCache>,
+}
+
+impl Vm {
+    pub fn new(config: &VmConfig) -> Self {
+        let engine = Self::engine();
+        let engine_id = Self::engine_id(&engine);
+
+        Self {
+            engine,
+            modules: Arc::new(ModuleCache::new(config.module_cache_size, engine_id)),
+        }
+    }
+
+    /// Persists compiled module artifacts to the storage so they survive restarts
+    pub fn with_artifact_storage(self, storage: Arc<dyn Storage<Vec<u8>, Vec<u8>>>) -> Self {
+        let modules = ModuleCache::new(self.modules.capacity(), self.modules.engine_id())
+            .with_storage(storage);
+
+        Self {
+            modules: Arc::new(modules),
+            ..self
+        }
+    }
+
+    /// Checks that an uploaded module is a wasm binary within the protocol limits which
+    /// imports only host functions and exports the `MemorySlice` ABI
+    pub fn validate(&self, wasm_bytes: &[u8]) -> Result<(), VmError> {
+        if !wasmer::is_wasm(wasm_bytes) {
+            return Err(VmError::NotWasm);
+        }
+
+        if wasm_bytes.len() > MAX_MODULE_SIZE {
+            return Err(VmError::ModuleTooLarge {
+                len: wasm_bytes.len(),
+                max_len: MAX_MODULE_SIZE,
+            });
+        }
+
+        let mut functions = 0;
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::FunctionSection(reader) =
+                payload.map_err(|e| VmError::Compilation(e.to_string()))?
+            {
+                functions = reader.count();
+            }
+        }
+        if functions > MAX_MODULE_FUNCTIONS {
+            return Err(VmError::TooManyFunctions {
+                count: functions,
+                max_count: MAX_MODULE_FUNCTIONS,
+            });
+        }
+
+        // compiling checks enabled proposals and imports, only valid modules are cached
+        let module = self.compile(wasm_bytes)?;
+
+        let export = |name: &str| {
+            module
+                .exports()
+                .find(|export| export.name() == name)
+                .map(|export| export.ty().clone())
+                .ok_or_else(|| VmError::MissingExport(name.to_owned()))
+        };
+
+        if !matches!(export("memory")?, ExternType::Memory(_)) {
+            return Err(VmError::InvalidExport(
+                "memory".to_owned(),
+                "memory".to_owned(),
+            ));
+        }
+        check_signature("allocate", export("allocate")?, &[Type::I32], &[Type::I32])?;
+        check_signature("deallocate", export("deallocate")?, &[Type::I32], &[])?;
+
+        self.modules.insert(module_hash(wasm_bytes), module);
+        Ok(())
+    }
+
+    /// Returns the cached module compiled from the wasm bytes, compiling it on a miss
+    pub fn load(&self, wasm_bytes: &[u8]) -> Result<Module, VmError> {
+        let hash = module_hash(wasm_bytes);
+        if let Some(module) = self.modules.get(&self.engine, &hash) {
+            return Ok(module);
+        }
+
+        let module = self.compile(wasm_bytes)?;
+        self.modules.insert(hash, module.clone());
+
+        Ok(module)
+    }
+
+    /// Compiles wasm bytes into a module that can be instantiated multiple times.
+    ///
+    /// Modules using disabled proposals or importing anything but the ramd host functions are
+    /// rejected.
+    pub fn compile(&self, wasm_bytes: &[u8]) -> Result<Module, VmError> {
+        // metering tracks the globals it injects into the module being compiled, so every
+        // module gets an engine of its own and is loaded into the shared one once compiled
+        let artifact = Module::new(&Self::engine(), wasm_bytes)
+            .map_err(|e| VmError::Compilation(e.to_string()))?
+            .serialize()
+            .map_err(|e| VmError::Compilation(e.to_string()))?;
+        // SAFETY: the artifact was just serialized by an engine of the same configuration
+        let module = unsafe { Module::deserialize(&self.engine, artifact) }
+            .map_err(|e| VmError::Compilation(e.to_string()))?;
+
+        for import in module.imports() {
+            let allowed = import.module() == HOST_MODULE
+                && HOST_FUNCTIONS.contains(&import.name())
+                && matches!(import.ty(), ExternType::Function(_));
+            if !allowed {
+                return Err(VmError::ForbiddenImport(format!(
+                    "{}::{}",
+                    import.module(),
+                    import.name()
+                )));
+            }
+        }
+
+        Ok(module)
+    }
+
+    /// Creates a fresh instance of the module with host functions bound to the environment
+    pub fn instantiate(&self, module: &Module, env: HostEnv) -> Result<Executor, VmError> {
+        let mut store = Store::new(self.engine.clone());
+
+        let (imports, env) = imports(&mut store, env);
+        let instance = Instance::new(&mut store, module, &imports)
+            .map_err(|e| VmError::Instantiation(e.to_string()))?;
+
+        let mut executor = Executor::new(store, instance, env)?;
+        let fuel = metering::fuel(&executor.instance)?;
+        executor.env.as_mut(&mut executor.store).init(
+            executor.memory.clone(),
+            executor.allocate.clone(),
+            fuel,
+        );
+
+        Ok(executor)
+    }
+
+    /// Loads and instantiates the module, then calls the method once
+    pub fn execute(
+        &self,
+        wasm_bytes: &[u8],
+        method: &str,
+        args: &[u8],
+        env: HostEnv,
+    ) -> Result<Vec<u8>, VmError> {
+        let module = self.load(wasm_bytes)?;
+        self.instantiate(&module, env)?.call(method, args)
+    }
+
+    /// Creates an engine compiling modules with the deterministic execution profile, fuel
+    /// metering and the protocol limits
+    fn engine() -> Engine {
+        // replicas must compute bit-identical results, so NaNs are canonicalized and
+        // shared memory with atomics, the only nondeterministic proposal we'd accept, is off
+        let mut compiler = Cranelift::default();
+        compiler.canonicalize_nans(true);
+        compiler.push_middleware(Arc::new(Metering::default()));
+
+        let mut features = Features::default();
+        features.threads(false);
+
+        let mut engine: Engine = EngineBuilder::new(compiler)
+            .set_features(Some(features))
+            .into();
+        let base = BaseTunables::for_target(engine.target());
+        engine.set_tunables(LimitingTunables::new(
+            base,
+            MAX_MEMORY_PAGES,
+            MAX_TABLE_ELEMENTS,
+        ));
+
+        engine
+    }
+
+    /// Identifies everything the compiled code depends on, artifacts of other engines are stale
+    fn engine_id(engine: &Engine) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(wasmer::VERSION)
+            .chain_update(PROFILE_VERSION.to_le_bytes())
+            .chain_update(engine.target().triple().to_string())
+            .chain_update(MAX_MEMORY_PAGES.to_le_bytes())
+            .chain_update(MAX_TABLE_ELEMENTS.to_le_bytes())
+            .finalize()
+            .into()
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new(&VmConfig::default())
+    }
+}
+
+/// Checks that the export is a function with the given signature
+fn check_signature(
+    name: &str,
+    ty: ExternType,
+    params: &[Type],
+    results: &[Type],
+) -> Result<(), VmError> {
+    match ty {
+        ExternType::Function(ty) if ty.params() == params && ty.results() == results => Ok(()),
+        _ => Err(VmError::InvalidExport(
+            name.to_owned(),
+            format!("function {params:?} -> {results:?}"),
+        )),
+    }
+}
+
+/// Instance of a live object module exposing the `MemorySlice` ABI.
+///
+/// The guest `allocate` export reserves memory for arguments, a method receives a pointer
+/// to that slice and returns a pointer to the result, which is released with `deallocate`.
+/// Every call, including the calls to `allocate` and `deallocate`, is metered.
+pub struct Executor {
+    store: Store,
+    instance: Instance,
+    env: FunctionEnv<HostEnv>,
+    memory: Memory,
+    allocate: TypedFunction<u32, u32>,
+    deallocate: TypedFunction<u32, ()>,
+    fuel_used: u64,
+}
+
+impl Executor {
+    fn new(store: Store, instance: Instance, env: FunctionEnv<HostEnv>) -> Result<Self, VmError> {
+        let memory = instance
+            .exports
+            .get_memory("memory")
+            .map_err(|_| VmError::MissingExport("memory".to_owned()))?
+            .clone();
+        let allocate = instance
+            .exports
+            .get_typed_function(&store, "allocate")
+            .map_err(|_| VmError::MissingExport("allocate".to_owned()))?;
+        let deallocate = instance
+            .exports
+            .get_typed_function(&store, "deallocate")
+            .map_err(|_| VmError::MissingExport("deallocate".to_owned()))?;
+
+        Ok(Self {
+            store,
+            instance,
+            env,
+            memory,
+            allocate,
+            deallocate,
+            fuel_used: 0,
+        })
+    }
+
+    /// Fuel consumed by the latest call
+    pub fn fuel_used(&self) -> u64 {
+        self.fuel_used
+    }
+
+    /// Returns the events emitted by the guest since the previous take
+    pub fn take_events(&mut self) -> Vec<Vec<u8>> {
+        self.env.as_mut(&mut self.store).take_events()
+    }
+
+    /// Calls exported method with given args and returns the result bytes
+    pub fn call(&mut self, method: &str, args: &[u8]) -> Result<Vec<u8>, VmError> {
+        let function: TypedFunction<u32, u32> = self
+            .instance
+            .exports
+            .get_typed_function(&self.store, method)
+            .map_err(|e| match e {
+                ExportError::IncompatibleType => {
+                    VmError::MethodNotFound(format!("{method} (incompatible signature)"))
+                }
+                ExportError::Missing(_) => VmError::MethodNotFound(method.to_owned()),
+            })?;
+
+        metering::set_fuel(&mut self.store, &self.instance, FUEL_LIMIT)?;
+        self.fuel_used = 0;
+
+        let result = self.call_metered(&function, args);
+
+        let remaining = metering::get_fuel(&mut self.store, &self.instance)?;
+        self.fuel_used = FUEL_LIMIT - remaining.unwrap_or(0);
+        if remaining.is_none() {
+            return Err(VmError::OutOfFuel(FUEL_LIMIT));
+        }
+
+        result
+    }
+
+    fn call_metered(
+        &mut self,
+        function: &TypedFunction<u32, u32>,
+        args: &[u8],
+    ) -> Result<Vec<u8>, VmError> {
+        let args_ptr = self.write_args(args)?;
+
+        let result_ptr = function
+            .call(&mut self.store, args_ptr)
+            .map_err(VmError::from)?;
+
+        let result = {
+            let view = self.memory.view(&self.store);
+            MemorySlice::new(&view, result_ptr)?.read(&view, MAX_RESULT_LEN)
+        };
+
+        self.deallocate
+            .call(&mut self.store, result_ptr)
+            .map_err(VmError::from)?;
+
+        Ok(result?)
+    }
+
+    /// Allocates guest memory for args and copies them into it
+    fn write_args(&mut self, args: &[u8]) -> Result<u32, VmError> {
+        let args_len = u32::try_from(args.len())
+            .map_err(|_| VmError::InvalidArgs("args are too large".to_owned()))?;
+
+        let args_ptr = self
+            .allocate
+            .call(&mut self.store, args_len)
+            .map_err(|e| VmError::InvalidArgs(e.to_string()))?;
+
+        let view = self.memory.view(&self.store);
+        MemorySlice::new(&view, args_ptr)?.write(&view, args)?;
+
+        Ok(args_ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::testing::{env, module, NAMESPACE};
+    use ramd_db::memory::MemoryStorage;
+
+    const ITER_IMPORT: &str =
+        r#"(import "ramd" "state_iter_prefix" (func $iter (param i32 i32) (result i32)))"#;
+
+    /// Returns the encoded entries under the empty prefix
+    const ITER_ALL: &str = r#"
+        (func (export "iter") (param i32) (result i32)
+            (call $iter (i32.const 0) (i32.const 0)))
+    "#;
+
+    fn store_entries(storage: &MemoryStorage, count: usize, value_len: usize) {
+        let entries = (0..count)
+            .map(|index| {
+                let key = [NAMESPACE, format!("{index:08}").as_bytes()].concat();
+                (key, Some(vec![7; value_len]))
+            })
+            .collect();
+        storage.write_batch(entries).unwrap();
+    }
+
+    #[test]
+    fn iterates_the_state_under_a_prefix() {
+        let storage = Arc::new(MemoryStorage::default());
+        store_entries(&storage, 2, 1);
+
+        let result = Vm::default()
+            .execute(&module(ITER_IMPORT, ITER_ALL), "iter", &[], env(&storage))
+            .unwrap();
+
+        let entry = |key: &[u8]| [&8u32.to_le_bytes(), key, &1u32.to_le_bytes(), &[7]].concat();
+        assert_eq!(result, [entry(b"00000000"), entry(b"00000001")].concat());
+    }
+
+    #[test]
+    fn iterating_a_large_state_runs_out_of_fuel() {
+        // far more bytes than the fuel limit pays for
+        let storage = Arc::new(MemoryStorage::default());
+        store_entries(&storage, 2_000, (FUEL_LIMIT / 1_000) as usize);
+
+        let result =
+            Vm::default().execute(&module(ITER_IMPORT, ITER_ALL), "iter", &[], env(&storage));
+
+        assert!(matches!(result, Err(VmError::OutOfFuel(FUEL_LIMIT))));
+    }
+
+    /// Instantiates the module over an empty state
+    fn instantiate(vm: &Vm, wasm_bytes: &[u8]) -> Result<Executor, VmError> {
+        let storage = Arc::new(MemoryStorage::default());
+        vm.instantiate(&vm.load(wasm_bytes)?, env(&storage))
+    }
+
+    /// Calls the method, returning the fuel it used
+    fn fuel_used(executor: &mut Executor, method: &str) -> u64 {
+        executor.call(method, &[]).unwrap();
+        executor.fuel_used()
+    }
+
+    /// Decodes a result made of `i32` LE values
+    fn i32s(result: &[u8]) -> Vec<i32> {
+        result
+            .chunks(4)
+            .map(|chunk| i32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Method returning a `MemorySlice` with the two given `i32` values
+    fn returning_pair(name: &str, first: &str, second: &str) -> String {
+        format!(
+            r#"
+            (func (export "{name}") (param i32) (result i32)
+                (i32.store (i32.const 64) (i32.const 72))
+                (i32.store offset=4 (i32.const 64) (i32.const 8))
+                (i32.store (i32.const 72) {first})
+                (i32.store offset=4 (i32.const 72) {second})
+                (i32.const 64))
+            "#
+        )
+    }
+
+    #[test]
+    fn runs_out_of_fuel() {
+        let wasm_bytes = module(
+            "",
+            r#"
+            (func (export "spin") (param i32) (result i32)
+                (loop $spin (br $spin))
+                (local.get 0))
+            (func (export "noop") (param i32) (result i32)
+                (local.get 0))
+            "#,
+        );
+        let vm = Vm::default();
+        let mut executor = instantiate(&vm, &wasm_bytes).unwrap();
+
+        assert!(matches!(
+            executor.call("spin", &[]),
+            Err(VmError::OutOfFuel(FUEL_LIMIT))
+        ));
+        assert_eq!(executor.fuel_used(), FUEL_LIMIT);
+
+        // every call gets the full fuel again
+        let used = fuel_used(&mut executor, "noop");
+        assert!(used > 0 && used < 1_000);
+    }
+
+    #[test]
+    fn caps_memory() {
+        let grow = returning_pair(
+            "grow_past",
+            &format!("(memory.grow (i32.const {MAX_MEMORY_PAGES}))"),
+            "(memory.size)",
+        ) + &returning_pair(
+            "grow_to",
+            &format!("(memory.grow (i32.const {}))", MAX_MEMORY_PAGES - 2),
+            "(memory.size)",
+        );
+        let vm = Vm::default();
+        let mut executor = instantiate(&vm, &module("", &grow)).unwrap();
+
+        assert_eq!(i32s(&executor.call("grow_past", &[]).unwrap()), vec![-1, 2]);
+        assert_eq!(
+            i32s(&executor.call("grow_to", &[]).unwrap()),
+            vec![2, MAX_MEMORY_PAGES as i32]
+        );
+
+        let declared = wat::parse_str(format!(
+            r#"(module
+                (memory (export "memory") {})
+                (func (export "allocate") (param i32) (result i32) (i32.const 0))
+                (func (export "deallocate") (param i32)))"#,
+            MAX_MEMORY_PAGES + 1
+        ))
+        .unwrap();
+        assert!(matches!(
+            instantiate(&vm, &declared),
+            Err(VmError::Instantiation(_))
+        ));
+    }
+
+    #[test]
+    fn caps_tables() {
+        let grow = format!(
+            "(table $table 1 funcref) {}",
+            returning_pair(
+                "grow",
+                &format!("(table.grow $table (ref.null func) (i32.const {MAX_TABLE_ELEMENTS}))"),
+                "(table.size $table)",
+            )
+        );
+        let vm = Vm::default();
+        let mut executor = instantiate(&vm, &module("", &grow)).unwrap();
+
+        assert_eq!(i32s(&executor.call("grow", &[]).unwrap()), vec![-1, 1]);
+
+        let declared = module("", &format!("(table {} funcref)", MAX_TABLE_ELEMENTS + 1));
+        assert!(matches!(
+            instantiate(&vm, &declared),
+            Err(VmError::Instantiation(_))
+        ));
+    }
+
+    #[test]
+    fn meters_bulk_operations_by_size() {
+        let wasm_bytes = module(
+            "",
+            &format!(
+                r#"
+                (func (export "fill_small") (param i32) (result i32)
+                    (memory.fill (i32.const 4096) (i32.const 7) (i32.const 10))
+                    (local.get 0))
+                (func (export "fill_big") (param i32) (result i32)
+                    (memory.fill (i32.const 4096) (i32.const 7) (i32.const 100000))
+                    (local.get 0))
+                (func (export "copy_big") (param i32) (result i32)
+                    (memory.copy (i32.const 4096) (i32.const 8192) (i32.const 100000))
+                    (local.get 0))
+                (func (export "fill_huge") (param i32) (result i32)
+                    (memory.fill (i32.const 0) (i32.const 7) (i32.const {}))
+                    (local.get 0))
+                "#,
+                FUEL_LIMIT + 1
+            ),
+        );
+        let vm = Vm::default();
+        let mut executor = instantiate(&vm, &wasm_bytes).unwrap();
+
+        let small = fuel_used(&mut executor, "fill_small");
+        assert_eq!(fuel_used(&mut executor, "fill_big") - small, 100_000 - 10);
+        assert_eq!(fuel_used(&mut executor, "copy_big") - small, 100_000 - 10);
+
+        // charged before it runs, so it runs out of fuel rather than out of bounds
+        assert!(matches!(
+            executor.call("fill_huge", &[]),
+            Err(VmError::OutOfFuel(FUEL_LIMIT))
+        ));
+    }
+
+    #[test]
+    fn compiles_modules_concurrently() {
+        let vm = Vm::default();
+
+        // modules with different numbers of globals get the metering globals at other indices
+        let handles: Vec<_> = (0..8)
+            .map(|globals| {
+                let vm = vm.clone();
+                std::thread::spawn(move || {
+                    let globals = "(global i32 (i32.const 0))".repeat(globals);
+                    let wasm_bytes = module(
+                        "",
+                        &format!(
+                            r#"{globals}
+                            (func (export "count") (param i32) (result i32)
+                                (local $i i32)
+                                (loop $count
+                                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                                    (br_if $count (i32.lt_u (local.get $i) (i32.const 1000))))
+                                (local.get 0))
+                            "#
+                        ),
+                    );
+
+                    let mut executor = instantiate(&vm, &wasm_bytes).unwrap();
+                    fuel_used(&mut executor, "count")
+                })
+            })
+            .collect();
+
+        let used: Vec<u64> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert!(used[0] > 1_000);
+        assert!(used.iter().all(|fuel| *fuel == used[0]));
+    }
+
+    #[test]
+    fn canonicalizes_nans() {
+        // adds one to the float passed as args, returning the bits of the sum
+        let wasm_bytes = module(
+            "",
+            r#"
+            (func (export "add32") (param $args i32) (result i32)
+                (i32.store (i32.const 64) (i32.const 72))
+                (i32.store offset=4 (i32.const 64) (i32.const 4))
+                (f32.store (i32.const 72)
+                    (f32.add (f32.load (i32.load (local.get $args))) (f32.const 1)))
+                (i32.const 64))
+            (func (export "add64") (param $args i32) (result i32)
+                (i32.store (i32.const 64) (i32.const 72))
+                (i32.store offset=4 (i32.const 64) (i32.const 8))
+                (f64.store (i32.const 72)
+                    (f64.add (f64.load (i32.load (local.get $args))) (f64.const 1)))
+                (i32.const 64))
+            "#,
+        );
+        let vm = Vm::default();
+        let mut executor = instantiate(&vm, &wasm_bytes).unwrap();
+
+        // negative NaNs with a payload, hardware would propagate sign and payload
+        let result = executor
+            .call("add32", &0xffc0_0001u32.to_le_bytes())
+            .unwrap();
+        assert_eq!(result, 0x7fc0_0000u32.to_le_bytes());
+
+        let result = executor
+            .call("add64", &0xfff8_0000_0000_0001u64.to_le_bytes())
+            .unwrap();
+        assert_eq!(result, 0x7ff8_0000_0000_0000u64.to_le_bytes());
+
+        // numbers are left alone
+        let result = executor.call("add32", &1f32.to_le_bytes()).unwrap();
+        assert_eq!(result, 2f32.to_le_bytes());
+    }
+
+    #[test]
+    fn rejects_threads() {
+        let vm = Vm::default();
+
+        let shared_memory = wat::parse_str("(module (memory 1 1 shared))").unwrap();
+        assert!(matches!(
+            vm.compile(&shared_memory),
+            Err(VmError::Compilation(_))
+        ));
+
+        let atomics = wat::parse_str(
+            r#"(module
+                (memory 1 1)
+                (func (result i32) (i32.atomic.load (i32.const 0))))"#,
+        )
+        .unwrap();
+        assert!(matches!(vm.compile(&atomics), Err(VmError::Compilation(_))));
+    }
+
+    /// Whether the module compiled from the bytes is cached
+    fn is_cached(vm: &Vm, wasm_bytes: &[u8]) -> bool {
+        vm.modules
+            .get(&vm.engine, &module_hash(wasm_bytes))
+            .is_some()
+    }
+
+    #[test]
+    fn accepts_and_caches_valid_modules() {
+        let vm = Vm::default();
+        let wasm_bytes = module(ITER_IMPORT, "");
+
+        vm.validate(&wasm_bytes).unwrap();
+        assert!(is_cached(&vm, &wasm_bytes));
+    }
+
+    #[test]
+    fn rejects_invalid_modules_without_caching_them() {
+        let vm = Vm::default();
+        let rejected = |wasm_bytes: &[u8]| {
+            let error = vm.validate(wasm_bytes).unwrap_err();
+            assert!(!is_cached(&vm, wasm_bytes));
+            error
+        };
+
+        assert!(matches!(rejected(b"not wasm"), VmError::NotWasm));
+
+        let oversized = [module("", "").as_slice(), &vec![0; MAX_MODULE_SIZE]].concat();
+        assert!(matches!(
+            rejected(&oversized),
+            VmError::ModuleTooLarge { .. }
+        ));
+
+        let functions = "(func)".repeat(MAX_MODULE_FUNCTIONS as usize);
+        assert!(matches!(
+            rejected(&module("", &functions)),
+            VmError::TooManyFunctions { .. }
+        ));
+
+        let forbidden = module(r#"(import "env" "abort" (func))"#, "");
+        assert!(matches!(
+            rejected(&forbidden),
+            VmError::ForbiddenImport(import) if import == "env::abort"
+        ));
+
+        let unknown_host_function = module(r#"(import "ramd" "exec" (func))"#, "");
+        assert!(matches!(
+            rejected(&unknown_host_function),
+            VmError::ForbiddenImport(_)
+        ));
+
+        let missing_export = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "allocate") (param i32) (result i32) (i32.const 0)))"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            rejected(&missing_export),
+            VmError::MissingExport(export) if export == "deallocate"
+        ));
+
+        let invalid_export = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "allocate") (result i32) (i32.const 0))
+                (func (export "deallocate") (param i32)))"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            rejected(&invalid_export),
+            VmError::InvalidExport(export, _) if export == "allocate"
+        ));
+    }
+}
@@ -1 +1,17 @@
+mod cache;
+mod error;
+mod executor;
+mod limits;
+mod metering;
+#[cfg(test)]
+mod testing;
+mod tunables;
 
+pub use crate::cache::{module_hash, ModuleHash};
+pub use crate::error::VmError;
+pub use crate::executor::{Executor, Vm};
+pub use crate::limits::{
+    FUEL_LIMIT, MAX_MEMORY_PAGES, MAX_MODULE_FUNCTIONS, MAX_MODULE_SIZE, MAX_RESULT_LEN,
+    MAX_TABLE_ELEMENTS,
+};
+pub use ramd_vm_runtime::{HostEnv, HostError, MemorySlice};
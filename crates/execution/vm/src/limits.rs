@@ -0,0 +1,21 @@
+//! Limits of the protocol. They decide whether a replicated call or module upload succeeds, so
+//! every replica must apply the same ones and they aren't configurable per node.
+
+/// Fuel available to a single live object call, every executed instruction costs one unit and
+/// host functions and bulk memory operations cost one unit per byte or entry they touch
+pub const FUEL_LIMIT: u64 = 10_000_000;
+
+/// Maximum number of 64KiB pages a live object linear memory may grow to
+pub const MAX_MEMORY_PAGES: u32 = 256;
+
+/// Maximum number of elements in a live object table
+pub const MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+/// Maximum size in bytes of the payload returned by a live object call
+pub const MAX_RESULT_LEN: usize = 64 * 1024;
+
+/// Maximum size in bytes of an uploaded live object module
+pub const MAX_MODULE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Maximum number of functions defined by an uploaded live object module
+pub const MAX_MODULE_FUNCTIONS: u32 = 10_000;
@@ -0,0 +1,246 @@
+use std::sync::Mutex;
+
+use crate::error::VmError;
+use ramd_vm_runtime::Fuel;
+use wasmer::wasmparser::{BlockType, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionMiddleware, Global, GlobalInit, GlobalType, Instance,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type, Value,
+};
+use wasmer_types::{GlobalIndex, LocalFunctionIndex, ModuleInfo};
+
+/// Export holding the fuel left for the current call
+const FUEL_REMAINING_EXPORT: &str = "ramd_fuel_remaining";
+/// Export set to 1 once the guest runs out of fuel
+const FUEL_EXHAUSTED_EXPORT: &str = "ramd_fuel_exhausted";
+
+/// Globals injected into the module being compiled
+#[derive(Clone, Copy, Debug)]
+struct FuelGlobals {
+    remaining: GlobalIndex,
+    exhausted: GlobalIndex,
+    /// Scratch slot holding the length operand of a bulk operation while it's charged
+    length: GlobalIndex,
+}
+
+/// Charges one unit of fuel for every executed operator and traps once the fuel runs out.
+///
+/// The fuel is kept in an injected global and charged at the end of every basic block, so
+/// exhaustion happens at the same point of execution on every node. Bulk memory and table
+/// operations are additionally charged one unit per byte or element right before they run.
+/// The injected globals are tracked for the module being compiled, so a middleware instance
+/// must only ever compile a single module.
+#[derive(Debug, Default)]
+pub struct Metering {
+    globals: Mutex<Option<FuelGlobals>>,
+}
+
+impl ModuleMiddleware for Metering {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        let globals = self
+            .globals
+            .lock()
+            .expect("metering lock is poisoned")
+            .expect("module info is transformed before functions");
+
+        Box::new(FunctionMetering {
+            globals,
+            accumulated: 0,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let remaining = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(0));
+        module_info.exports.insert(
+            FUEL_REMAINING_EXPORT.to_owned(),
+            ExportIndex::Global(remaining),
+        );
+
+        let exhausted = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+        module_info.exports.insert(
+            FUEL_EXHAUSTED_EXPORT.to_owned(),
+            ExportIndex::Global(exhausted),
+        );
+
+        let length = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        *self.globals.lock().expect("metering lock is poisoned") = Some(FuelGlobals {
+            remaining,
+            exhausted,
+            length,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct FunctionMetering {
+    globals: FuelGlobals,
+    /// Cost of the operators fed since the last charge
+    accumulated: u64,
+}
+
+impl FunctionMiddleware for FunctionMetering {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        self.accumulated += 1;
+
+        // the length of the touched region is the last operand of every bulk operation
+        let is_bulk = matches!(
+            operator,
+            Operator::MemoryFill { .. }
+                | Operator::MemoryCopy { .. }
+                | Operator::MemoryInit { .. }
+                | Operator::TableFill { .. }
+                | Operator::TableCopy { .. }
+                | Operator::TableInit { .. }
+        );
+        if is_bulk {
+            self.charge_length(state);
+        }
+
+        let ends_block = matches!(
+            operator,
+            Operator::Loop { .. }
+                | Operator::End
+                | Operator::If { .. }
+                | Operator::Else
+                | Operator::Br { .. }
+                | Operator::BrIf { .. }
+                | Operator::BrTable { .. }
+                | Operator::Unreachable
+                | Operator::Return
+                | Operator::Call { .. }
+                | Operator::CallIndirect { .. }
+                | Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. }
+        );
+
+        if ends_block && self.accumulated > 0 {
+            self.charge(
+                state,
+                &[Operator::I64Const {
+                    value: self.accumulated as i64,
+                }],
+            );
+
+            self.accumulated = 0;
+        }
+
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+impl FunctionMetering {
+    /// Charges the cost pushed by the given operators, trapping if the fuel runs out
+    fn charge<'a>(&self, state: &mut MiddlewareReaderState<'a>, cost: &[Operator<'a>]) {
+        let remaining = self.globals.remaining.as_u32();
+        let exhausted = self.globals.exhausted.as_u32();
+
+        state.push_operator(Operator::GlobalGet {
+            global_index: remaining,
+        });
+        state.extend(cost);
+        state.extend(&[
+            Operator::I64LtU,
+            Operator::If {
+                blockty: BlockType::Empty,
+            },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet {
+                global_index: exhausted,
+            },
+            Operator::Unreachable,
+            Operator::End,
+            Operator::GlobalGet {
+                global_index: remaining,
+            },
+        ]);
+        state.extend(cost);
+        state.extend(&[
+            Operator::I64Sub,
+            Operator::GlobalSet {
+                global_index: remaining,
+            },
+        ]);
+    }
+
+    /// Charges the `i32` length on top of the stack, leaving the stack as it was
+    fn charge_length(&self, state: &mut MiddlewareReaderState<'_>) {
+        let length = self.globals.length.as_u32();
+
+        state.push_operator(Operator::GlobalSet {
+            global_index: length,
+        });
+        self.charge(
+            state,
+            &[
+                Operator::GlobalGet {
+                    global_index: length,
+                },
+                Operator::I64ExtendI32U,
+            ],
+        );
+        state.push_operator(Operator::GlobalGet {
+            global_index: length,
+        });
+    }
+}
+
+/// Resets the fuel of the instance before a call
+pub fn set_fuel(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+    fuel: u64,
+) -> Result<(), VmError> {
+    fuel_global(instance, FUEL_REMAINING_EXPORT)?
+        .set(store, Value::I64(fuel as i64))
+        .map_err(|e| VmError::Trap(e.to_string()))?;
+    fuel_global(instance, FUEL_EXHAUSTED_EXPORT)?
+        .set(store, Value::I32(0))
+        .map_err(|e| VmError::Trap(e.to_string()))
+}
+
+/// Returns the fuel left, `None` if the instance ran out of fuel
+pub fn get_fuel(store: &mut impl AsStoreMut, instance: &Instance) -> Result<Option<u64>, VmError> {
+    let exhausted = fuel_global(instance, FUEL_EXHAUSTED_EXPORT)?.get(store);
+    if exhausted.i32() != Some(0) {
+        return Ok(None);
+    }
+
+    let remaining = fuel_global(instance, FUEL_REMAINING_EXPORT)?.get(store);
+    Ok(remaining.i64().map(|fuel| fuel as u64))
+}
+
+/// Returns the fuel globals of the instance, for host functions to charge their work
+pub fn fuel(instance: &Instance) -> Result<Fuel, VmError> {
+    Ok(Fuel {
+        remaining: fuel_global(instance, FUEL_REMAINING_EXPORT)?.clone(),
+        exhausted: fuel_global(instance, FUEL_EXHAUSTED_EXPORT)?.clone(),
+    })
+}
+
+fn fuel_global<'a>(instance: &'a Instance, name: &str) -> Result<&'a Global, VmError> {
+    instance
+        .exports
+        .get_global(name)
+        .map_err(|_| VmError::MissingExport(name.to_owned()))
+}
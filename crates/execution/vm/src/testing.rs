@@ -0,0 +1,34 @@
+//! Helpers building live object modules for unit tests
+
+use std::sync::Arc;
+
+use ramd_db::memory::MemoryStorage;
+use ramd_vm_runtime::HostEnv;
+
+/// Namespace of the live object state in the test storage
+pub(crate) const NAMESPACE: &[u8] = b"state/";
+
+/// Memory, bump allocator and `deallocate` of the `MemorySlice` ABI, every module needs them
+const ABI: &str = r#"
+    (memory (export "memory") 2)
+    (global $bump (mut i32) (i32.const 1024))
+    (func $allocate (export "allocate") (param $len i32) (result i32)
+        (local $slice i32)
+        (local.set $slice (global.get $bump))
+        (i32.store (local.get $slice) (i32.add (local.get $slice) (i32.const 8)))
+        (i32.store offset=4 (local.get $slice) (local.get $len))
+        (global.set $bump
+            (i32.add (global.get $bump) (i32.add (local.get $len) (i32.const 8))))
+        (local.get $slice))
+    (func (export "deallocate") (param i32))
+"#;
+
+/// Wasm binary of a live object with the ABI exports, `imports` come first as wat requires
+pub(crate) fn module(imports: &str, body: &str) -> Vec<u8> {
+    wat::parse_str(format!("(module {imports} {ABI} {body})")).expect("test module is valid wat")
+}
+
+/// Host environment over the storage, namespaced by [`NAMESPACE`]
+pub(crate) fn env(storage: &Arc<MemoryStorage>) -> HostEnv {
+    HostEnv::new(storage.clone(), NAMESPACE.to_vec())
+}
@@ -0,0 +1,115 @@
+use std::ptr::NonNull;
+
+use wasmer::sys::BaseTunables;
+use wasmer::vm::{
+    MemoryError, MemoryStyle, TableStyle, VMMemory, VMMemoryDefinition, VMTable, VMTableDefinition,
+};
+use wasmer::{MemoryType, Pages, TableType, Tunables};
+
+/// Caps the linear memory and tables a live object module may declare or grow to
+pub struct LimitingTunables {
+    base: BaseTunables,
+    max_memory_pages: Pages,
+    max_table_elements: u32,
+}
+
+impl LimitingTunables {
+    pub fn new(base: BaseTunables, max_memory_pages: u32, max_table_elements: u32) -> Self {
+        Self {
+            base,
+            max_memory_pages: Pages(max_memory_pages),
+            max_table_elements,
+        }
+    }
+
+    /// Bounds the maximum of the memory by the limit, so `memory.grow` fails past it
+    fn adjust_memory(&self, ty: &MemoryType) -> MemoryType {
+        let mut adjusted = *ty;
+        adjusted.maximum = Some(
+            ty.maximum
+                .map_or(self.max_memory_pages, |max| max.min(self.max_memory_pages)),
+        );
+        adjusted
+    }
+
+    fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+        if ty.minimum > self.max_memory_pages {
+            return Err(MemoryError::Generic(format!(
+                "Minimum of {} pages exceeds the limit of {} pages",
+                ty.minimum.0, self.max_memory_pages.0
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Bounds the maximum of the table by the limit, so `table.grow` fails past it
+    fn adjust_table(&self, ty: &TableType) -> TableType {
+        let mut adjusted = *ty;
+        adjusted.maximum = Some(ty.maximum.map_or(self.max_table_elements, |max| {
+            max.min(self.max_table_elements)
+        }));
+        adjusted
+    }
+
+    fn validate_table(&self, ty: &TableType) -> Result<(), String> {
+        if ty.minimum > self.max_table_elements {
+            return Err(format!(
+                "Minimum of {} table elements exceeds the limit of {}",
+                ty.minimum, self.max_table_elements
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Tunables for LimitingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(&self.adjust_memory(memory))
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base.create_host_memory(&adjusted, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base
+            .create_vm_memory(&adjusted, style, vm_definition_location)
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        let adjusted = self.adjust_table(ty);
+        self.validate_table(&adjusted)?;
+        self.base.create_host_table(&adjusted, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        let adjusted = self.adjust_table(ty);
+        self.validate_table(&adjusted)?;
+        self.base
+            .create_vm_table(&adjusted, style, vm_definition_location)
+    }
+}
@@ -1,3 +1,73 @@
+use ramd_processor::{DeadLetter, LiveObjectId, Message, MessageId, Query, Receipt, Role};
+
 pub trait LiveObjectHandler: Send + Sync {
-    fn create_live_object(&self, wasm_bytes: Vec<u8>);
+    /// Creates a live object from wasm bytes, owned by the node account, and returns its ID
+    fn create_live_object(&self, wasm_bytes: Vec<u8>) -> eyre::Result<LiveObjectId>;
+
+    /// Calls a method of the live object and returns ID of the created message
+    fn execute_live_object(
+        &self,
+        live_object_id: LiveObjectId,
+        method: String,
+        args: Vec<u8>,
+    ) -> eyre::Result<MessageId>;
+
+    /// Replaces the code of the live object, optionally migrating its state, and returns
+    /// ID of the created message
+    fn upgrade_live_object(
+        &self,
+        live_object_id: LiveObjectId,
+        wasm_bytes: Vec<u8>,
+        migrate_args: Option<Vec<u8>>,
+    ) -> eyre::Result<MessageId>;
+
+    /// Grants the role on the live object and returns ID of the created message
+    fn grant_role(
+        &self,
+        live_object_id: LiveObjectId,
+        grantee: Vec<u8>,
+        role: Role,
+    ) -> eyre::Result<MessageId>;
+
+    /// Revokes the role of the grantee and returns ID of the created message
+    fn revoke_role(
+        &self,
+        live_object_id: LiveObjectId,
+        grantee: Vec<u8>,
+    ) -> eyre::Result<MessageId>;
+
+    /// Processes a message signed by a client and returns its ID, it's broadcast once applied
+    fn submit_message(&self, message: Message) -> eyre::Result<MessageId>;
+
+    /// Returns IDs of the latest messages applied to the live object, the predecessors of
+    /// the next message
+    fn live_object_heads(&self, live_object_id: LiveObjectId) -> eyre::Result<Vec<MessageId>>;
+
+    /// Calls a read-only method of the live object with the role of the query sender, who
+    /// must have signed it recently, and returns its result
+    fn query_live_object(&self, query: Query) -> eyre::Result<Vec<u8>>;
+
+    /// Returns the receipt of the message, `None` if it wasn't processed yet
+    fn message_receipt(&self, message_id: MessageId) -> eyre::Result<Option<Receipt>>;
+
+    /// Starts replicating the live object and fetches its messages from other replicas
+    fn replicate_live_object(&self, live_object_id: LiveObjectId) -> eyre::Result<()>;
+
+    /// Stops replicating the live object, its state stays available
+    fn stop_replicating_live_object(&self, live_object_id: LiveObjectId) -> eyre::Result<()>;
+
+    /// Returns IDs of the live objects replicated by the node
+    fn replicated_live_objects(&self) -> eyre::Result<Vec<LiveObjectId>>;
+}
+
+pub trait DeadLetterHandler: Send + Sync {
+    /// Returns messages quarantined after they failed to be processed
+    fn dead_letters(&self) -> eyre::Result<Vec<DeadLetter>>;
+
+    /// Processes the quarantined message again and returns its receipt, `None` if it's still
+    /// waiting for predecessors
+    fn retry_dead_letter(&self, message_id: MessageId) -> eyre::Result<Option<Receipt>>;
+
+    /// Removes the quarantined message, or all of them if `None`, and returns the number removed
+    fn purge_dead_letters(&self, message_id: Option<MessageId>) -> eyre::Result<usize>;
 }
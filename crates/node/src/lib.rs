@@ -1,5 +1,7 @@
 mod handlers;
 mod node;
+mod validator;
 
-pub use handlers::LiveObjectHandler;
+pub use handlers::{DeadLetterHandler, LiveObjectHandler};
 pub use node::Node;
+pub use validator::MessageValidator;
@@ -1,41 +1,823 @@
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::handlers::LiveObjectHandler;
-use ramd_config::configs::node::NodeConfig;
-use ramd_db::storage::Storage;
-use ramd_processor::{Action, CreateLiveObjectAction, Message, Processor};
-use tracing::info;
+use async_channel::Sender;
+
+use crate::handlers::{DeadLetterHandler, LiveObjectHandler};
+use ed25519_dalek::SigningKey;
+use ramd_config::configs::{node::NodeConfig, processor::ProcessorConfig, vm::VmConfig};
+use ramd_db::{
+    keys::{
+        outbox_key, replicated_key, RAMD_ACCOUNT_KEY, RAMD_OUTBOX_PREFIX, RAMD_REPLICATED_PREFIX,
+    },
+    storage::Storage,
+};
+use ramd_p2p_types::{
+    command::P2pCommand,
+    message::{P2pMessage, MAX_SYNC_MESSAGE_IDS, MAX_SYNC_RESPONSE_LEN},
+};
+use ramd_processor::{
+    Action, CreateLiveObjectAction, DeadLetter, ExecuteLiveObjectAction, GrantRoleAction,
+    LiveObjectId, Message, MessageId, Processor, ProcessorError, Query, Receipt, ReplayReport,
+    RevokeRoleAction, Role, UpgradeLiveObjectAction,
+};
+use tracing::{debug, info, warn};
+
+/// Number of replayed messages between progress logs
+const REPLAY_PROGRESS_INTERVAL: usize = 1_000;
+
+/// Seconds a signed query stays valid, in either direction to tolerate clock skew
+const QUERY_MAX_AGE_SECS: u64 = 60;
 
 pub struct Node<S>
 where
     S: Storage<Vec<u8>, Vec<u8>>,
 {
     processor: Processor<S>,
+    storage: Arc<S>,
+    /// Signs messages created by this node, separate from the p2p identity
+    account: SigningKey,
+    /// Requests to the p2p server, messages accepted locally are broadcast through it
+    network: Sender<P2pCommand>,
 }
 
 impl<S> Node<S>
 where
-    S: Storage<Vec<u8>, Vec<u8>>,
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
 {
-    pub fn new(_config: &NodeConfig, storage: Arc<S>) -> eyre::Result<Self> {
-        Ok(Node {
-            processor: Processor::new(storage.clone()),
-        })
+    pub fn new(
+        _config: &NodeConfig,
+        processor_config: &ProcessorConfig,
+        vm_config: &VmConfig,
+        storage: Arc<S>,
+        network: Sender<P2pCommand>,
+    ) -> eyre::Result<Self> {
+        let account = Self::get_account_key(storage.as_ref())?;
+        info!(target: "ramd::node", "Signing messages with account `{}`", hex::encode(account.verifying_key()));
+
+        let node = Node {
+            processor: Processor::new(processor_config, vm_config, storage.clone()),
+            storage,
+            account,
+            network,
+        };
+
+        if processor_config.replay_on_start {
+            node.replay(None)?;
+        }
+
+        Ok(node)
+    }
+
+    /// Rebuilds the state of the live object, or of all live objects if `None`, from stored
+    /// messages, see [`Processor::replay`]
+    pub fn replay(&self, live_object_id: Option<LiveObjectId>) -> eyre::Result<ReplayReport> {
+        let report = self.processor.replay(live_object_id.as_ref(), |progress| {
+            if progress.processed % REPLAY_PROGRESS_INTERVAL == 0 || progress.processed == progress.total {
+                info!(target: "ramd::node", "Replayed {} of {} messages", progress.processed, progress.total);
+            }
+        })?;
+
+        if report.is_consistent() {
+            info!(target: "ramd::node", "Rebuilt {} live objects from {} messages", report.live_objects, report.replayed);
+        } else {
+            warn!(target: "ramd::node", "Rebuilt {} live objects, {} messages failed, {} were skipped and {} state hashes differ", report.live_objects, report.failed.len(), report.skipped.len(), report.mismatched.len());
+        }
+
+        Ok(report)
+    }
+
+    /// Handles a message received from another replica, applying new messages and answering
+    /// sync requests addressed to this node
+    pub fn receive_p2p_message(&self, p2p_msg: P2pMessage) -> eyre::Result<()> {
+        match p2p_msg {
+            P2pMessage::NewMessage {
+                live_object_id,
+                message,
+            } => self
+                .receive_messages(live_object_id, vec![message])
+                .map(|_| ()),
+            P2pMessage::SyncResponse {
+                live_object_id,
+                messages,
+                truncated,
+                ..
+            } => {
+                let received = self.receive_messages(live_object_id, messages)?;
+
+                // the rest is found through the heads of the responder, a round bringing
+                // nothing new would only repeat itself
+                if truncated && received > 0 {
+                    self.sync(live_object_id, vec![])?;
+                } else if truncated {
+                    debug!(target: "ramd::node", "Truncated sync response of live object `{}` brought no new messages, stopping", hex::encode(live_object_id));
+                }
+
+                Ok(())
+            }
+            P2pMessage::ModuleAnnouncement {
+                live_object_id,
+                version,
+                module_hash,
+            } => {
+                let versions = self.processor.live_object_versions(&live_object_id)?;
+                let is_known = versions
+                    .iter()
+                    .any(|known| known.version == version && known.module_hash == module_hash);
+
+                if !is_known {
+                    info!(target: "ramd::node", "Live object `{}` is behind version {}, requesting heads", hex::encode(live_object_id), version);
+                    self.sync(live_object_id, vec![])?;
+                }
+
+                Ok(())
+            }
+            P2pMessage::DagHeads {
+                live_object_id,
+                heads,
+                ..
+            } => {
+                let unknown = self.unknown_messages(heads)?;
+                if !unknown.is_empty() {
+                    self.sync(live_object_id, unknown)?;
+                }
+
+                Ok(())
+            }
+            P2pMessage::SyncRequest {
+                live_object_id,
+                nonce,
+                message_ids,
+                ..
+            } => {
+                if message_ids.is_empty() {
+                    let mut heads = self.processor.live_object_heads(&live_object_id)?;
+                    heads.truncate(MAX_SYNC_MESSAGE_IDS);
+
+                    return self.publish(P2pMessage::DagHeads {
+                        live_object_id,
+                        nonce,
+                        heads,
+                    });
+                }
+
+                let mut messages = Vec::new();
+                let mut len = 0;
+                let mut truncated = false;
+                for message_id in message_ids.iter().take(MAX_SYNC_MESSAGE_IDS) {
+                    let Some(message) = self.processor.message(message_id)? else {
+                        continue;
+                    };
+
+                    let message = bincode::serialize(&message)?;
+                    if !messages.is_empty() && len + message.len() > MAX_SYNC_RESPONSE_LEN {
+                        truncated = true;
+                        break;
+                    }
+
+                    len += message.len();
+                    messages.push(message);
+                }
+
+                if messages.is_empty() {
+                    return Ok(());
+                }
+
+                self.publish(P2pMessage::SyncResponse {
+                    live_object_id,
+                    nonce,
+                    messages,
+                    truncated,
+                })
+            }
+        }
+    }
+
+    /// Applies bincode encoded messages of the live object received from another replica and
+    /// requests predecessors they are waiting for. Returns the number of messages which were
+    /// new to this node.
+    fn receive_messages(
+        &self,
+        live_object_id: LiveObjectId,
+        encoded: Vec<Vec<u8>>,
+    ) -> eyre::Result<usize> {
+        let mut messages = Vec::with_capacity(encoded.len());
+        for message in encoded {
+            let message: Message = bincode::deserialize(&message)?;
+            if message.action.live_object_id() != live_object_id {
+                return Err(eyre::eyre!(
+                    "Message `{}` doesn't belong to live object `{}`",
+                    message.id_hex(),
+                    hex::encode(live_object_id)
+                ));
+            }
+
+            info!(target: "ramd::node", "Received message `{}` for live object `{}`", message.id_hex(), hex::encode(live_object_id));
+            messages.push(message);
+        }
+
+        let received = self
+            .unknown_messages(messages.iter().map(|message| message.id).collect())?
+            .len();
+
+        // ID and signature are verified by the processor
+        self.processor.process_messages(&messages);
+
+        // messages of this node waiting for these ones may be applied now
+        self.flush_outbox();
+
+        let pending = self.processor.pending_dependencies();
+        let missing: Vec<MessageId> = messages
+            .iter()
+            .flat_map(|message| message.predecessors.iter())
+            .filter(|id| pending.contains(*id))
+            .copied()
+            .collect();
+
+        if !missing.is_empty() {
+            debug!(target: "ramd::node", "Requesting {} missing predecessors of live object `{}`", missing.len(), hex::encode(live_object_id));
+            self.sync(live_object_id, missing)?;
+        }
+
+        Ok(received)
+    }
+
+    /// Messages this node has neither applied nor pooled
+    fn unknown_messages(&self, message_ids: Vec<MessageId>) -> eyre::Result<Vec<MessageId>> {
+        let mut unknown = Vec::new();
+        for message_id in message_ids {
+            if self.processor.message(&message_id)?.is_none()
+                && !self.processor.is_pooled(&message_id)
+            {
+                unknown.push(message_id);
+            }
+        }
+
+        Ok(unknown)
+    }
+
+    /// Starts replicating the live object, receiving its gossip and fetching the messages
+    /// this node is missing from the other replicas
+    pub fn replicate(&self, live_object_id: LiveObjectId) -> eyre::Result<()> {
+        self.subscribe(live_object_id)?;
+
+        // a replica answers with its heads, unknown messages are requested from there
+        self.sync(live_object_id, vec![])
+    }
+
+    /// Stops receiving gossip of the live object, messages applied so far are kept
+    pub fn stop_replicating(&self, live_object_id: LiveObjectId) -> eyre::Result<()> {
+        info!(target: "ramd::node", "Stopping replication of live object `{}`", hex::encode(live_object_id));
+
+        self.storage.delete(replicated_key(&live_object_id))?;
+        self.network
+            .try_send(P2pCommand::Unsubscribe { live_object_id })?;
+
+        Ok(())
+    }
+
+    /// IDs of the live objects replicated by this node
+    pub fn replicated_live_objects(&self) -> eyre::Result<Vec<LiveObjectId>> {
+        self.storage
+            .iter_prefix(Vec::from(RAMD_REPLICATED_PREFIX))?
+            .into_iter()
+            .map(|(key, _)| Ok(key[RAMD_REPLICATED_PREFIX.len()..].try_into()?))
+            .collect()
+    }
+
+    /// Remembers the live object as replicated, so it's resumed after a restart, and
+    /// subscribes to its gossip
+    fn subscribe(&self, live_object_id: LiveObjectId) -> eyre::Result<()> {
+        info!(target: "ramd::node", "Replicating live object `{}`", hex::encode(live_object_id));
+
+        self.storage.set(replicated_key(&live_object_id), vec![])?;
+        self.network
+            .try_send(P2pCommand::Subscribe { live_object_id })?;
+
+        Ok(())
+    }
+
+    /// Public key of the account authoring messages of this node
+    pub fn account_public_key(&self) -> Vec<u8> {
+        self.account.verifying_key().to_bytes().to_vec()
+    }
+
+    /// If the account key was already created then recover it from the storage,
+    /// otherwise create a new one and store it
+    fn get_account_key(storage: &S) -> eyre::Result<SigningKey> {
+        if let Some(sk) = storage.get_opt(RAMD_ACCOUNT_KEY.into())? {
+            let sk: [u8; 32] = sk
+                .try_into()
+                .map_err(|_| eyre::eyre!("Stored account key is malformed"))?;
+
+            Ok(SigningKey::from_bytes(&sk))
+        } else {
+            let sk = SigningKey::from_bytes(&rand::random());
+            storage.set(RAMD_ACCOUNT_KEY.into(), Vec::from(sk.to_bytes()))?;
+
+            Ok(sk)
+        }
+    }
+
+    /// Creates a message on top of the live object heads and processes it
+    fn submit(&self, action: Action) -> eyre::Result<MessageId> {
+        // new message causally follows everything applied to the live object so far
+        let predecessors = self.processor.live_object_heads(&action.live_object_id())?;
+
+        let message = Message::new(action, predecessors, &self.account);
+
+        info!(target: "ramd::node", "New message `{}` for live object `{}`", message.id_hex(), hex::encode(message.action.live_object_id()));
+
+        self.enqueue(message)
+    }
+
+    /// Processes a message created by this node or submitted by a client, and broadcasts it
+    /// once it's applied
+    fn enqueue(&self, message: Message) -> eyre::Result<MessageId> {
+        let message_id = message.id;
+
+        // broadcast once applied, even if that only happens on a retry
+        self.storage.set(outbox_key(&message_id), vec![])?;
+
+        self.processor.process_messages(&[message]);
+        self.flush_outbox();
+
+        Ok(message_id)
+    }
+
+    /// Broadcasts the messages submitted to this node which are applied by now, whether they
+    /// were applied right away, released from the pool or retried as dead letters.
+    /// Replicas missing a broadcast catch up through sync, so failures are only logged.
+    fn flush_outbox(&self) {
+        let outbox = match self.storage.iter_prefix(Vec::from(RAMD_OUTBOX_PREFIX)) {
+            Ok(outbox) => outbox,
+            Err(e) => {
+                warn!(target: "ramd::node", "Failed to read messages to broadcast with error `{}`", e.to_string());
+                return;
+            }
+        };
+
+        for (key, _) in outbox {
+            let Ok(message_id) = MessageId::try_from(&key[RAMD_OUTBOX_PREFIX.len()..]) else {
+                continue;
+            };
+
+            // still waiting in the pool or for a retry of its dead letter
+            let message = match self.processor.message(&message_id) {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    self.drop_abandoned(key, &message_id);
+                    continue;
+                }
+                Err(e) => {
+                    warn!(target: "ramd::node", "Failed to read message `{}` with error `{}`", hex::encode(message_id), e.to_string());
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.broadcast(&message) {
+                warn!(target: "ramd::node", "Failed to broadcast message `{}` with error `{}`", message.id_hex(), e.to_string());
+            }
+
+            if let Err(e) = self.storage.delete(key) {
+                warn!(target: "ramd::node", "Failed to remove message `{}` from the outbox with error `{}`", message.id_hex(), e.to_string());
+            }
+        }
+    }
+
+    /// Removes the outbox entry of a message which failed and isn't retried automatically,
+    /// it's only applied again if its dead letter is retried by hand
+    fn drop_abandoned(&self, key: Vec<u8>, message_id: &MessageId) {
+        let abandoned = self.processor.dead_letter(message_id).map(|dead_letter| {
+            dead_letter.is_some_and(|dead_letter| !self.processor.is_retried(&dead_letter))
+        });
+
+        match abandoned {
+            Ok(true) => {
+                debug!(target: "ramd::node", "Message `{}` was rejected, it won't be broadcast", hex::encode(message_id));
+                if let Err(e) = self.storage.delete(key) {
+                    warn!(target: "ramd::node", "Failed to remove message `{}` from the outbox with error `{}`", hex::encode(message_id), e.to_string());
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                warn!(target: "ramd::node", "Failed to read dead letter `{}` with error `{}`", hex::encode(message_id), e.to_string());
+            }
+        }
+    }
+
+    /// Publishes the applied message to the other replicas of its live object
+    fn broadcast(&self, message: &Message) -> eyre::Result<()> {
+        let live_object_id = message.action.live_object_id();
+        debug!(target: "ramd::node", "Broadcasting message `{}`", message.id_hex());
+        self.publish(P2pMessage::NewMessage {
+            live_object_id,
+            message: bincode::serialize(message)?,
+        })?;
+
+        // replicas lagging behind learn about new code even if they missed the message
+        if matches!(
+            message.action,
+            Action::CreateLiveObject(_) | Action::UpgradeLiveObject(_)
+        ) {
+            let deployed = self
+                .processor
+                .live_object_versions(&live_object_id)?
+                .into_iter()
+                .find(|version| version.message_id == message.id);
+
+            if let Some(deployed) = deployed {
+                self.publish(P2pMessage::ModuleAnnouncement {
+                    live_object_id,
+                    version: deployed.version,
+                    module_hash: deployed.module_hash,
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publishes the message to the other replicas of its live object
+    fn publish(&self, message: P2pMessage) -> eyre::Result<()> {
+        self.network.try_send(P2pCommand::Publish { message })?;
+
+        Ok(())
+    }
+
+    /// Asks a replica of the live object for the messages, or for its heads if none are given
+    fn sync(&self, live_object_id: LiveObjectId, message_ids: Vec<MessageId>) -> eyre::Result<()> {
+        self.network.try_send(P2pCommand::Sync {
+            live_object_id,
+            message_ids,
+        })?;
+
+        Ok(())
     }
 }
 
 impl<S> LiveObjectHandler for Node<S>
 where
-    S: Storage<Vec<u8>, Vec<u8>>,
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
 {
-    fn create_live_object(&self, wasm_bytes: Vec<u8>) {
-        let messages = vec![Message {
-            action: Action::CreateLiveObject(CreateLiveObjectAction { wasm_bytes }),
-        }];
+    fn create_live_object(&self, wasm_bytes: Vec<u8>) -> eyre::Result<LiveObjectId> {
+        // reject garbage before it's stored and replicated
+        self.processor.validate_module(&wasm_bytes)?;
 
-        // TODO: log message ID.
-        info!(target: "ramd::node", "New message with create action");
+        // creation time makes sure re-uploading the same module results in a new live object
+        let nonce = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
 
-        self.processor.process_messages(&messages);
+        let action = CreateLiveObjectAction {
+            wasm_bytes,
+            creator: self.account_public_key(),
+            nonce,
+        };
+        let live_object_id = action.live_object_id();
+
+        // a new live object has no heads, so the message doesn't depend on any prior message
+        self.submit(Action::CreateLiveObject(action))?;
+
+        // the creator replicates the live object from now on, there is nothing to sync yet
+        self.subscribe(live_object_id)?;
+
+        Ok(live_object_id)
+    }
+
+    fn execute_live_object(
+        &self,
+        live_object_id: LiveObjectId,
+        method: String,
+        args: Vec<u8>,
+    ) -> eyre::Result<MessageId> {
+        self.submit(Action::ExecuteLiveObject(ExecuteLiveObjectAction {
+            live_object_id,
+            method,
+            args,
+        }))
+    }
+
+    fn upgrade_live_object(
+        &self,
+        live_object_id: LiveObjectId,
+        wasm_bytes: Vec<u8>,
+        migrate_args: Option<Vec<u8>>,
+    ) -> eyre::Result<MessageId> {
+        self.processor.validate_module(&wasm_bytes)?;
+
+        self.submit(Action::UpgradeLiveObject(UpgradeLiveObjectAction {
+            live_object_id,
+            wasm_bytes,
+            migrate_args,
+        }))
+    }
+
+    fn grant_role(
+        &self,
+        live_object_id: LiveObjectId,
+        grantee: Vec<u8>,
+        role: Role,
+    ) -> eyre::Result<MessageId> {
+        self.submit(Action::GrantRole(GrantRoleAction {
+            live_object_id,
+            grantee,
+            role,
+        }))
+    }
+
+    fn revoke_role(
+        &self,
+        live_object_id: LiveObjectId,
+        grantee: Vec<u8>,
+    ) -> eyre::Result<MessageId> {
+        self.submit(Action::RevokeRole(RevokeRoleAction {
+            live_object_id,
+            grantee,
+        }))
+    }
+
+    fn submit_message(&self, message: Message) -> eyre::Result<MessageId> {
+        if !message.has_valid_id() {
+            return Err(ProcessorError::InvalidMessage(
+                message.id_hex(),
+                "ID doesn't match its content".to_owned(),
+            )
+            .into());
+        }
+        if !message.has_valid_signature() {
+            return Err(ProcessorError::InvalidMessage(
+                message.id_hex(),
+                "signature isn't made by the author".to_owned(),
+            )
+            .into());
+        }
+
+        // reject garbage before it's stored and replicated
+        let creates = match &message.action {
+            Action::CreateLiveObject(action) => {
+                self.processor.validate_module(&action.wasm_bytes)?;
+                true
+            }
+            Action::UpgradeLiveObject(action) => {
+                self.processor.validate_module(&action.wasm_bytes)?;
+                false
+            }
+            _ => false,
+        };
+
+        let live_object_id = message.action.live_object_id();
+        info!(target: "ramd::node", "Submitted message `{}` for live object `{}`", message.id_hex(), hex::encode(live_object_id));
+
+        let message_id = self.enqueue(message)?;
+
+        // like live objects created by the node account, there is nothing to sync yet
+        if creates {
+            self.subscribe(live_object_id)?;
+        }
+
+        Ok(message_id)
+    }
+
+    fn live_object_heads(&self, live_object_id: LiveObjectId) -> eyre::Result<Vec<MessageId>> {
+        self.processor.live_object_heads(&live_object_id)
+    }
+
+    fn query_live_object(&self, query: Query) -> eyre::Result<Vec<u8>> {
+        let sender = hex::encode(&query.sender);
+        if !query.has_valid_signature() {
+            debug!(target: "ramd::node", "Rejected query of `{}` with an invalid signature", sender);
+            return Err(ProcessorError::Unauthorized(sender).into());
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if now.abs_diff(query.timestamp) > QUERY_MAX_AGE_SECS {
+            debug!(target: "ramd::node", "Rejected query of `{}` signed at {}", sender, query.timestamp);
+            return Err(ProcessorError::Unauthorized(sender).into());
+        }
+
+        Ok(self.processor.query(
+            &query.live_object_id,
+            &query.method,
+            &query.args,
+            &query.sender,
+        )?)
+    }
+
+    fn message_receipt(&self, message_id: MessageId) -> eyre::Result<Option<Receipt>> {
+        self.processor.receipt(&message_id)
+    }
+
+    fn replicate_live_object(&self, live_object_id: LiveObjectId) -> eyre::Result<()> {
+        self.replicate(live_object_id)
+    }
+
+    fn stop_replicating_live_object(&self, live_object_id: LiveObjectId) -> eyre::Result<()> {
+        self.stop_replicating(live_object_id)
+    }
+
+    fn replicated_live_objects(&self) -> eyre::Result<Vec<LiveObjectId>> {
+        Node::replicated_live_objects(self)
+    }
+}
+
+impl<S> DeadLetterHandler for Node<S>
+where
+    S: Storage<Vec<u8>, Vec<u8>> + 'static,
+{
+    fn dead_letters(&self) -> eyre::Result<Vec<DeadLetter>> {
+        self.processor.dead_letters()
+    }
+
+    fn retry_dead_letter(&self, message_id: MessageId) -> eyre::Result<Option<Receipt>> {
+        info!(target: "ramd::node", "Retrying dead letter `{}`", hex::encode(message_id));
+
+        // broadcast once applied, its outbox entry was dropped when it was rejected
+        if self.processor.dead_letter(&message_id)?.is_some() {
+            self.storage.set(outbox_key(&message_id), vec![])?;
+        }
+
+        let receipt = self.processor.retry_dead_letter(&message_id)?;
+        self.flush_outbox();
+
+        Ok(receipt)
+    }
+
+    fn purge_dead_letters(&self, message_id: Option<MessageId>) -> eyre::Result<usize> {
+        let purged: Vec<MessageId> = match message_id {
+            Some(message_id) => vec![message_id],
+            None => self
+                .processor
+                .dead_letters()?
+                .into_iter()
+                .map(|dead_letter| dead_letter.message.id)
+                .collect(),
+        };
+
+        let count = self.processor.purge_dead_letters(message_id.as_ref())?;
+
+        // purged messages of this node are never applied, so never broadcast
+        self.storage.write_batch(
+            purged
+                .iter()
+                .map(|message_id| (outbox_key(message_id), None))
+                .collect(),
+        )?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_channel::Receiver;
+    use ramd_db::memory::MemoryStorage;
+
+    /// Live object returning its arguments from `run`
+    const ECHO: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $bump (mut i32) (i32.const 1024))
+            (func (export "allocate") (param $len i32) (result i32)
+                (local $slice i32)
+                (local.set $slice (global.get $bump))
+                (i32.store (local.get $slice) (i32.add (local.get $slice) (i32.const 8)))
+                (i32.store offset=4 (local.get $slice) (local.get $len))
+                (global.set $bump
+                    (i32.add (global.get $bump) (i32.add (local.get $len) (i32.const 8))))
+                (local.get $slice))
+            (func (export "deallocate") (param i32))
+            (func (export "run") (param $args i32) (result i32)
+                (local.get $args)))
+    "#;
+
+    fn node() -> (Node<MemoryStorage>, Receiver<P2pCommand>) {
+        let (network, commands) = async_channel::unbounded();
+        let node = Node::new(
+            &NodeConfig::default(),
+            &ProcessorConfig::default(),
+            &VmConfig::default(),
+            Arc::new(MemoryStorage::default()),
+            network,
+        )
+        .unwrap();
+
+        (node, commands)
+    }
+
+    /// IDs of the messages waiting in the outbox
+    fn outbox(node: &Node<MemoryStorage>) -> Vec<MessageId> {
+        Storage::<_, Vec<u8>>::iter_prefix(node.storage.as_ref(), Vec::from(RAMD_OUTBOX_PREFIX))
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key[RAMD_OUTBOX_PREFIX.len()..].try_into().unwrap())
+            .collect()
+    }
+
+    /// IDs of the messages broadcast since the last call
+    fn broadcast(commands: &Receiver<P2pCommand>) -> Vec<MessageId> {
+        std::iter::from_fn(|| commands.try_recv().ok())
+            .filter_map(|command| match command {
+                P2pCommand::Publish {
+                    message: P2pMessage::NewMessage { message, .. },
+                } => Some(bincode::deserialize::<Message>(&message).unwrap().id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Message IDs of the sync requests since the last call
+    fn syncs(commands: &Receiver<P2pCommand>) -> Vec<Vec<MessageId>> {
+        std::iter::from_fn(|| commands.try_recv().ok())
+            .filter_map(|command| match command {
+                P2pCommand::Sync { message_ids, .. } => Some(message_ids),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn broadcasts_applied_messages_once() {
+        let (node, commands) = node();
+        let live_object_id = node
+            .create_live_object(wat::parse_str(ECHO).unwrap())
+            .unwrap();
+        let created = node.live_object_heads(live_object_id).unwrap();
+        assert_eq!(broadcast(&commands), created);
+
+        let executed = node
+            .execute_live_object(live_object_id, "run".to_owned(), vec![1])
+            .unwrap();
+        assert_eq!(broadcast(&commands), [executed]);
+        assert!(outbox(&node).is_empty());
+
+        // released later, the message is broadcast once its predecessors are applied
+        let (earlier, pooled) = {
+            let author = &node.account;
+            let action = |args: Vec<u8>| {
+                Action::ExecuteLiveObject(ExecuteLiveObjectAction {
+                    live_object_id,
+                    method: "run".to_owned(),
+                    args,
+                })
+            };
+            let earlier = Message::new(action(vec![2]), vec![executed], author);
+            let pooled = Message::new(action(vec![3]), vec![earlier.id], author);
+            (earlier, pooled)
+        };
+        node.submit_message(pooled.clone()).unwrap();
+        assert_eq!(outbox(&node), [pooled.id]);
+        assert!(broadcast(&commands).is_empty());
+
+        node.receive_p2p_message(P2pMessage::NewMessage {
+            live_object_id,
+            message: bincode::serialize(&earlier).unwrap(),
+        })
+        .unwrap();
+        assert_eq!(broadcast(&commands), [pooled.id]);
+        assert!(outbox(&node).is_empty());
+    }
+
+    #[test]
+    fn drops_rejected_messages_from_the_outbox() {
+        let (node, commands) = node();
+
+        // the live object doesn't exist, the failure isn't retried automatically
+        let rejected = node
+            .execute_live_object([1; 32], "run".to_owned(), vec![])
+            .unwrap();
+        assert!(node.processor.dead_letter(&rejected).unwrap().is_some());
+        assert!(outbox(&node).is_empty());
+        assert!(broadcast(&commands).is_empty());
+
+        // retried by hand it gets another chance, failing again drops it again
+        let receipt = node.retry_dead_letter(rejected).unwrap().unwrap();
+        assert!(!receipt.is_success());
+        assert!(outbox(&node).is_empty());
+        assert!(broadcast(&commands).is_empty());
+    }
+
+    #[test]
+    fn stops_syncing_once_truncated_responses_bring_nothing_new() {
+        let (replica, _replica_commands) = node();
+        let live_object_id = replica
+            .create_live_object(wat::parse_str(ECHO).unwrap())
+            .unwrap();
+        let created = replica.live_object_heads(live_object_id).unwrap()[0];
+        let created = replica.processor.message(&created).unwrap().unwrap();
+
+        let (node, commands) = node();
+        let response = P2pMessage::SyncResponse {
+            live_object_id,
+            nonce: 0,
+            messages: vec![bincode::serialize(&created).unwrap()],
+            truncated: true,
+        };
+
+        // the rest is asked for through the heads of a replica
+        node.receive_p2p_message(response.clone()).unwrap();
+        assert_eq!(syncs(&commands), [Vec::<MessageId>::new()]);
+
+        node.receive_p2p_message(response).unwrap();
+        assert!(syncs(&commands).is_empty());
     }
 }
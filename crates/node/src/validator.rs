@@ -0,0 +1,165 @@
+use ramd_p2p_types::{
+    message::{P2pMessage, P2pMessageError},
+    validator::P2pValidator,
+};
+use ramd_processor::{LiveObjectId, Message};
+
+/// Checks live object messages carried by gossip are authentic and belong to the topic's
+/// live object, so forged messages aren't forwarded to other replicas
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessageValidator;
+
+impl P2pValidator for MessageValidator {
+    fn validate(&self, message: &P2pMessage) -> Result<(), P2pMessageError> {
+        match message {
+            P2pMessage::NewMessage {
+                live_object_id,
+                message,
+            } => validate_message(live_object_id, message),
+            P2pMessage::SyncResponse {
+                live_object_id,
+                messages,
+                ..
+            } => messages
+                .iter()
+                .try_for_each(|message| validate_message(live_object_id, message)),
+            P2pMessage::ModuleAnnouncement { .. }
+            | P2pMessage::DagHeads { .. }
+            | P2pMessage::SyncRequest { .. } => Ok(()),
+        }
+    }
+}
+
+/// Decodes the bincode encoded message and checks its ID, signature and live object
+fn validate_message(live_object_id: &LiveObjectId, message: &[u8]) -> Result<(), P2pMessageError> {
+    let message: Message =
+        bincode::deserialize(message).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+
+    if !message.has_valid_id() {
+        return Err(P2pMessageError::Invalid(format!(
+            "ID `{}` doesn't match the message content",
+            message.id_hex()
+        )));
+    }
+
+    if !message.has_valid_signature() {
+        return Err(P2pMessageError::Invalid(format!(
+            "message `{}` has an invalid signature",
+            message.id_hex()
+        )));
+    }
+
+    if message.action.live_object_id() != *live_object_id {
+        return Err(P2pMessageError::Invalid(format!(
+            "message `{}` belongs to live object `{}`",
+            message.id_hex(),
+            hex::encode(message.action.live_object_id())
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use ramd_processor::{Action, ExecuteLiveObjectAction};
+
+    const LIVE_OBJECT: LiveObjectId = [1; 32];
+
+    fn message(live_object_id: LiveObjectId, args: &[u8]) -> Message {
+        let action = Action::ExecuteLiveObject(ExecuteLiveObjectAction {
+            live_object_id,
+            method: "run".to_owned(),
+            args: args.to_vec(),
+        });
+
+        Message::new(action, vec![], &SigningKey::from_bytes(&[1; 32]))
+    }
+
+    fn new_message(message: &Message) -> P2pMessage {
+        P2pMessage::NewMessage {
+            live_object_id: LIVE_OBJECT,
+            message: bincode::serialize(message).unwrap(),
+        }
+    }
+
+    fn sync_response(messages: &[Message]) -> P2pMessage {
+        P2pMessage::SyncResponse {
+            live_object_id: LIVE_OBJECT,
+            nonce: 0,
+            messages: messages
+                .iter()
+                .map(|message| bincode::serialize(message).unwrap())
+                .collect(),
+            truncated: false,
+        }
+    }
+
+    fn is_invalid(message: &P2pMessage) -> bool {
+        matches!(
+            MessageValidator.validate(message),
+            Err(P2pMessageError::Invalid(_))
+        )
+    }
+
+    #[test]
+    fn accepts_authentic_messages() {
+        let (first, second) = (message(LIVE_OBJECT, &[1]), message(LIVE_OBJECT, &[2]));
+
+        assert!(MessageValidator.validate(&new_message(&first)).is_ok());
+        assert!(MessageValidator
+            .validate(&sync_response(&[first, second]))
+            .is_ok());
+        assert!(MessageValidator
+            .validate(&P2pMessage::DagHeads {
+                live_object_id: LIVE_OBJECT,
+                nonce: 0,
+                heads: vec![[2; 32]],
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let message = P2pMessage::NewMessage {
+            live_object_id: LIVE_OBJECT,
+            message: vec![1, 2, 3],
+        };
+
+        assert!(matches!(
+            MessageValidator.validate(&message),
+            Err(P2pMessageError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_forged_messages() {
+        let authentic = message(LIVE_OBJECT, &[1]);
+
+        let mut tampered = authentic.clone();
+        tampered.action = message(LIVE_OBJECT, &[2]).action;
+        assert!(is_invalid(&new_message(&tampered)));
+
+        let mut tampered_id = authentic.clone();
+        tampered_id.id = [2; 32];
+        assert!(is_invalid(&new_message(&tampered_id)));
+
+        let mut bad_signature = authentic;
+        bad_signature.signature[0] ^= 1;
+        assert!(is_invalid(&new_message(&bad_signature)));
+    }
+
+    #[test]
+    fn rejects_messages_of_other_live_objects() {
+        let other = message([2; 32], &[1]);
+        assert!(is_invalid(&new_message(&other)));
+
+        // a single foreign message spoils the whole response
+        assert!(is_invalid(&sync_response(&[
+            message(LIVE_OBJECT, &[1]),
+            other
+        ])));
+    }
+}
@@ -0,0 +1,74 @@
+use ramd_p2p_types::message::MAX_TRANSMIT_SIZE;
+
+/// Magic number every zstd frame starts with, encoded payloads never start with it
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression level, favors speed since every gossip message is compressed on the hot path
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Largest payload a compressed frame may expand to, guards against decompression bombs.
+/// Anything a peer may send uncompressed is accepted compressed as well.
+const MAX_DECOMPRESSED_LEN: usize = MAX_TRANSMIT_SIZE;
+
+/// Whether the payload is a zstd frame
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+/// Compresses the payload into a single zstd frame
+pub fn compress(data: &[u8]) -> eyre::Result<Vec<u8>> {
+    Ok(zstd::bulk::compress(data, COMPRESSION_LEVEL)?)
+}
+
+/// Decompresses a zstd frame produced by [`compress`], frames expanding past
+/// [`MAX_DECOMPRESSED_LEN`] are rejected
+pub fn decompress(data: &[u8]) -> eyre::Result<Vec<u8>> {
+    Ok(zstd::bulk::decompress(data, MAX_DECOMPRESSED_LEN)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = b"ramd ".repeat(1024);
+
+        let compressed = compress(&data).unwrap();
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn accepts_the_size_limit() {
+        let data = vec![0; MAX_DECOMPRESSED_LEN];
+
+        let compressed = compress(&data).unwrap();
+        assert_eq!(decompress(&compressed).unwrap().len(), MAX_DECOMPRESSED_LEN);
+    }
+
+    #[test]
+    fn round_trips_the_largest_gossip_payload() {
+        let data: Vec<u8> = (0..MAX_TRANSMIT_SIZE).map(|i| (i % 251) as u8).collect();
+
+        let compressed = compress(&data).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_frames_past_the_size_limit() {
+        let compressed = compress(&vec![0; MAX_DECOMPRESSED_LEN + 1]).unwrap();
+
+        assert!(decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let mut data = ZSTD_MAGIC.to_vec();
+        data.extend_from_slice(b"not a frame");
+
+        assert!(is_compressed(&data));
+        assert!(decompress(&data).is_err());
+    }
+}
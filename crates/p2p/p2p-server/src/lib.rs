@@ -1,3 +1,4 @@
+mod compression;
 mod server;
 
 pub use server::*;
@@ -1,26 +1,56 @@
 use async_channel::{Receiver, Sender};
 use futures::prelude::*;
 use libp2p::{
-    gossipsub::{self, IdentTopic},
+    gossipsub::{self, IdentTopic, MessageAcceptance, PublishError, TopicHash},
     identify, identity,
     kad::{self, Mode},
     noise,
     swarm::{NetworkBehaviour, SwarmEvent},
     tcp, yamux, Multiaddr, PeerId,
 };
+use rand::seq::SliceRandom;
 
-use ramd_config::configs::network::P2pConfig;
+use crate::compression::{compress, decompress, is_compressed};
+use ramd_config::configs::network::{P2pConfig, WireEncoding};
 use ramd_db::{keys::RAMD_P2P_KEYPAIR_KEY, storage::Storage};
-use ramd_p2p_types::message::P2pMessage;
+use ramd_p2p_types::{
+    codec::{decode_any, BincodeCodec, JsonCodec, P2pCodec},
+    command::P2pCommand,
+    message::{P2pMessage, P2pMessageError, MAX_SYNC_MESSAGE_IDS, MAX_TRANSMIT_SIZE},
+    topic::{live_object_topic, topic_live_object_id},
+    validator::P2pValidator,
+};
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
     str::FromStr,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tracing::{debug, error, info, warn};
 
+/// How often publications waiting for peers are retried
+const PUBLISH_RETRY_TICK: Duration = Duration::from_millis(100);
+
+/// How long the response to a sync request is awaited
+const SYNC_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publication retried once peers subscribe to its topic
+struct PendingPublish {
+    topic: IdentTopic,
+    data: Vec<u8>,
+    attempts: u32,
+    retry_at: Instant,
+}
+
+/// Sync request retried once a replica of the live object is connected
+struct PendingSync {
+    live_object_id: [u8; 32],
+    message_ids: Vec<[u8; 32]>,
+    attempts: u32,
+    retry_at: Instant,
+}
+
 #[derive(NetworkBehaviour)]
 struct RamdBehavior {
     gossipsub: gossipsub::Behaviour,
@@ -35,16 +65,44 @@ where
     _storage: Arc<S>,
     swarm: libp2p::Swarm<RamdBehavior>,
     boot_nodes: Vec<PeerId>,
-    topic: IdentTopic,
+    /// Topics of the replicated live objects
+    topics: HashMap<TopicHash, [u8; 32]>,
     max_peers_limit: usize,
-    msg_receiver: Receiver<P2pMessage>,
+    /// Malformed or invalid messages a peer may send before it's disconnected
+    max_invalid_messages: u32,
+    /// Malformed or invalid messages received so far per peer
+    invalid_messages: HashMap<PeerId, u32>,
+    cmd_receiver: Receiver<P2pCommand>,
+    /// Valid messages received from peers go to the node
+    inbound_sender: Sender<P2pMessage>,
+    /// Checks the content of received messages, which this server can't interpret
+    validator: Box<dyn P2pValidator>,
+    /// Publications which found no peers, retried with exponential backoff
+    pending_publishes: Vec<PendingPublish>,
+    publish_max_retries: u32,
+    publish_retry_backoff: Duration,
+    /// Sync requests which found no replica, retried like publications
+    pending_syncs: Vec<PendingSync>,
+    /// Live object and send time of the sync requests awaiting a response, by nonce
+    sync_requests: HashMap<u64, ([u8; 32], Instant)>,
+    /// Codec of sent payloads, received payloads are decoded with the codec they're tagged with
+    codec: Box<dyn P2pCodec>,
+    /// Payloads of at least this many bytes are compressed
+    compression_threshold: Option<usize>,
 }
 
 impl<S> Server<S>
 where
     S: Storage<Vec<u8>, Vec<u8>>,
 {
-    pub fn new(p2p_cfg: &P2pConfig, storage: Arc<S>) -> eyre::Result<(Self, Sender<P2pMessage>)> {
+    /// The server isn't subscribed to any topic until the node requests live objects it replicates.
+    /// Messages received from peers which pass the `validator` are sent to `inbound_sender`.
+    pub fn new(
+        p2p_cfg: &P2pConfig,
+        storage: Arc<S>,
+        inbound_sender: Sender<P2pMessage>,
+        validator: Box<dyn P2pValidator>,
+    ) -> eyre::Result<(Self, Sender<P2pCommand>)> {
         let node_key = Self::get_node_key(storage.clone())?;
 
         let mut swarm = libp2p::SwarmBuilder::with_existing_identity(node_key)
@@ -67,7 +125,11 @@ where
                 let gossipsub_config = gossipsub::ConfigBuilder::default()
                     .heartbeat_interval(Duration::from_secs(10)) // This is set to aid debugging by not cluttering the log space
                     .validation_mode(gossipsub::ValidationMode::Strict)
+                    // messages are forwarded to other peers only once they are validated
+                    .validate_messages()
                     .message_id_fn(message_id_fn)
+                    // create and upgrade messages carry whole modules
+                    .max_transmit_size(MAX_TRANSMIT_SIZE)
                     .build()
                     .map_err(|msg| std::io::Error::new(std::io::ErrorKind::Other, msg))?;
 
@@ -97,11 +159,6 @@ where
             })
             .build();
 
-        // Subscribe to configured topic
-        // TODO: remove this. topics should be created for ea LO, no need for a generic one
-        let topic = IdentTopic::new("ramd");
-        swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
-
         // Adding boot node addresses for initial peer discovery
         let boot_nodes = p2p_cfg
             .boot_nodes
@@ -125,39 +182,69 @@ where
         swarm.listen_on(format!("/ip4/0.0.0.0/tcp/{}", p2p_cfg.port).parse()?)?;
 
         // Create channel for communicating with p2p module
-        let (msg_sender, msg_receiver) = async_channel::unbounded();
+        let (cmd_sender, cmd_receiver) = async_channel::unbounded();
 
         Ok((
             Self {
                 _storage: storage,
                 swarm,
                 boot_nodes,
-                topic,
+                topics: HashMap::new(),
                 max_peers_limit: p2p_cfg.max_peers_limit,
-                msg_receiver,
+                max_invalid_messages: p2p_cfg.max_invalid_messages,
+                invalid_messages: HashMap::new(),
+                cmd_receiver,
+                inbound_sender,
+                validator,
+                pending_publishes: Vec::new(),
+                publish_max_retries: p2p_cfg.publish_max_retries,
+                publish_retry_backoff: p2p_cfg.publish_retry_backoff(),
+                pending_syncs: Vec::new(),
+                sync_requests: HashMap::new(),
+                codec: match p2p_cfg.encoding {
+                    WireEncoding::Bincode => Box::new(BincodeCodec),
+                    WireEncoding::Json => Box::new(JsonCodec),
+                },
+                compression_threshold: p2p_cfg.compression_threshold,
             },
-            msg_sender,
+            cmd_sender,
         ))
     }
 
     pub async fn launch(&mut self) {
+        let mut publish_retry_timer = tokio::time::interval(PUBLISH_RETRY_TICK);
+
         loop {
             tokio::select! {
-                // ramd request for broadcasting a message
-                Ok(ramd_msg) = self.msg_receiver.recv() => {
-                    let Ok(msg) = serde_json::to_string(&ramd_msg) else {
-                        error!(target: "ramd::p2p", "Failed to serialize P2pMessage struct. Received message: {:?}", ramd_msg);
-                        continue;
-                    };
-
-                    // Try to broadcast message to connected nodes
-                    if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(
-                        self.topic.clone(),
-                        msg.as_bytes(), // TODO: for now just forward the message
-                    ) {
-                        error!("Failed to broadcast due to: {}", e.to_string());
-                    }
+                _ = publish_retry_timer.tick(), if !self.pending_publishes.is_empty() || !self.pending_syncs.is_empty() => {
+                    self.retry_publishes();
                 }
+                // ramd request to the p2p server
+                Ok(cmd) = self.cmd_receiver.recv() => match cmd {
+                    P2pCommand::Subscribe { live_object_id } => self.subscribe(live_object_id),
+                    P2pCommand::Unsubscribe { live_object_id } => self.unsubscribe(live_object_id),
+                    P2pCommand::Publish { message } => {
+                        let msg = match self.encode(&message) {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                error!(target: "ramd::p2p", "Failed to serialize P2pMessage struct due to: {}. Received message: {:?}", e, message);
+                                continue;
+                            }
+                        };
+
+                        // Try to broadcast message to replicas of the live object
+                        let topic = IdentTopic::new(live_object_topic(message.live_object_id()));
+                        self.publish(topic, msg, 0);
+                    }
+                    P2pCommand::Sync { live_object_id, message_ids } => {
+                        if message_ids.is_empty() {
+                            self.request_sync(live_object_id, vec![], 0);
+                        }
+                        for message_ids in message_ids.chunks(MAX_SYNC_MESSAGE_IDS) {
+                            self.request_sync(live_object_id, Vec::from(message_ids), 0);
+                        }
+                    }
+                },
                 // libp2p related events
                 event = self.swarm.select_next_some() => match event {
                     // Event from local server, logging locally assigned
@@ -233,12 +320,9 @@ where
                         message_id: id,
                         message,
                     })) => {
-                        info!("GOSSIP: Received gossipsub message. peer {}, id {}, data {}", peer_id, id, std::str::from_utf8(&message.data).unwrap());
+                        debug!(target: "ramd::p2p", "GOSSIP: Received gossipsub message. peer {}, id {}, {} bytes", peer_id, id, message.data.len());
 
-                        // first validate that received message is received from the right topic
-                        if message.topic != self.topic.hash() {
-                            self.disconnect_peer(&peer_id);
-                        }
+                        self.handle_gossip_message(id, peer_id, message).await;
                     }
                     SwarmEvent::Behaviour(RamdBehaviorEvent::Gossipsub(gossipsub::Event::Subscribed {
                         peer_id,
@@ -246,8 +330,8 @@ where
                     })) => {
                         debug!("GOSSIP: New peer subscribed to topic. peer {}, topic {}", peer_id, topic);
 
-                        // if connected peer is not a boot node and the topic is wrong - disconnect from it
-                        if !self.is_boot_node(&peer_id) && topic != self.topic.hash() {
+                        // peers replicate other live objects too, but must stick to live object topics
+                        if !self.is_boot_node(&peer_id) && topic_live_object_id(topic.as_str()).is_none() {
                             self.disconnect_peer(&peer_id);
                         }
                     }
@@ -273,6 +357,295 @@ where
         }
     }
 
+    /// Validates the received message, valid ones are forwarded to peers and sent to the node,
+    /// peers sending malformed or invalid ones are penalized
+    async fn handle_gossip_message(
+        &mut self,
+        id: gossipsub::MessageId,
+        peer_id: PeerId,
+        message: gossipsub::Message,
+    ) {
+        let acceptance = match self.validate(&message) {
+            Ok(Some(p2p_msg)) => match self.inbound_sender.send(p2p_msg).await {
+                Ok(_) => MessageAcceptance::Accept,
+                Err(e) => {
+                    error!(target: "ramd::p2p", "Failed to pass received message to the node due to: {}", e.to_string());
+                    MessageAcceptance::Ignore
+                }
+            },
+            Ok(None) => MessageAcceptance::Ignore,
+            Err(e) => {
+                warn!(target: "ramd::p2p", "GOSSIP: Rejected invalid message from peer {} due to: {}", peer_id, e);
+                MessageAcceptance::Reject
+            }
+        };
+
+        let is_rejected = matches!(acceptance, MessageAcceptance::Reject);
+        if let Err(e) = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(&id, &peer_id, acceptance)
+        {
+            error!(target: "ramd::p2p", "Failed to report validation result of message {} due to: {}", id, e.to_string());
+        }
+
+        if is_rejected {
+            self.penalize_peer(&peer_id);
+        }
+    }
+
+    /// Decodes the payload, checks it belongs to the topic it was received from and passes
+    /// the validator. Returns `None` for valid messages the node has no use for, such as sync
+    /// messages addressed to other replicas.
+    fn validate(&mut self, message: &gossipsub::Message) -> Result<Option<P2pMessage>, String> {
+        let Some(topic_live_object_id) = topic_live_object_id(message.topic.as_str()) else {
+            return Err(format!("unknown topic {}", message.topic));
+        };
+
+        // may still arrive shortly after unsubscribing
+        if !self.topics.contains_key(&message.topic) {
+            debug!(target: "ramd::p2p", "GOSSIP: Ignoring message of not replicated topic {}", message.topic);
+            return Ok(None);
+        }
+
+        let p2p_msg = match self.decode(&message.data) {
+            Ok(p2p_msg) => p2p_msg,
+            // peers running another version aren't misbehaving, their messages are skipped
+            Err(P2pMessageError::UnsupportedVersion(version)) => {
+                debug!(target: "ramd::p2p", "GOSSIP: Ignoring message of protocol version {}", version);
+                return Ok(None);
+            }
+            Err(P2pMessageError::UnsupportedEncoding(tag)) => {
+                debug!(target: "ramd::p2p", "GOSSIP: Ignoring message of encoding tag {}", tag);
+                return Ok(None);
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+
+        if *p2p_msg.live_object_id() != topic_live_object_id {
+            return Err(format!(
+                "message of another live object on topic {}",
+                message.topic
+            ));
+        }
+
+        p2p_msg.check_limits().map_err(|e| e.to_string())?;
+
+        match &p2p_msg {
+            P2pMessage::SyncRequest { responder, .. }
+                if *responder != self.swarm.local_peer_id().to_bytes() =>
+            {
+                return Ok(None);
+            }
+            P2pMessage::DagHeads { nonce, .. } | P2pMessage::SyncResponse { nonce, .. } => {
+                match self.sync_requests.get(nonce) {
+                    Some((live_object_id, _)) if *live_object_id == topic_live_object_id => {
+                        self.sync_requests.remove(nonce);
+                    }
+                    // response to another replica, or arriving after the timeout
+                    _ => return Ok(None),
+                }
+            }
+            _ => {}
+        }
+
+        self.validator
+            .validate(&p2p_msg)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(p2p_msg))
+    }
+
+    /// Encodes the message with the configured codec, compressing large payloads
+    fn encode(&self, message: &P2pMessage) -> eyre::Result<Vec<u8>> {
+        let data = self.codec.encode(message)?;
+
+        match self.compression_threshold {
+            Some(threshold) if data.len() >= threshold => {
+                let compressed = compress(&data)?;
+                debug!(target: "ramd::p2p", "Compressed payload from {} to {} bytes", data.len(), compressed.len());
+
+                Ok(compressed)
+            }
+            _ => Ok(data),
+        }
+    }
+
+    /// Decodes the payload whatever codec the peer encoded it with, compressed payloads are
+    /// accepted whatever the local threshold is
+    fn decode(&self, data: &[u8]) -> Result<P2pMessage, P2pMessageError> {
+        if is_compressed(data) {
+            let data = decompress(data).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+            return decode_any(&data);
+        }
+
+        decode_any(data)
+    }
+
+    /// Counts an invalid message of the peer, disconnecting it once the limit is reached
+    fn penalize_peer(&mut self, peer_id: &PeerId) {
+        let invalid_messages = self.invalid_messages.entry(*peer_id).or_default();
+        *invalid_messages += 1;
+
+        if *invalid_messages >= self.max_invalid_messages {
+            warn!(target: "ramd::p2p", "Peer {} sent {} invalid messages. Disconnecting", peer_id, invalid_messages);
+
+            self.invalid_messages.remove(peer_id);
+            self.disconnect_peer(peer_id);
+        }
+    }
+
+    /// Publishes the data, retrying later if no peer is subscribed to the topic yet
+    fn publish(&mut self, topic: IdentTopic, data: Vec<u8>, attempts: u32) {
+        match self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic.clone(), data.clone())
+        {
+            Ok(id) => {
+                debug!(target: "ramd::p2p", "Published message {} to topic {}", id, topic);
+            }
+            Err(PublishError::InsufficientPeers) if attempts < self.publish_max_retries => {
+                let backoff = self
+                    .publish_retry_backoff
+                    .saturating_mul(1 << attempts.min(16));
+                debug!(target: "ramd::p2p", "No peers on topic {}, retrying in {:?}", topic, backoff);
+
+                self.pending_publishes.push(PendingPublish {
+                    topic,
+                    data,
+                    attempts: attempts + 1,
+                    retry_at: Instant::now() + backoff,
+                });
+            }
+            Err(e) => {
+                error!(target: "ramd::p2p", "Failed to broadcast to topic {} after {} retries due to: {}", topic, attempts, e.to_string());
+            }
+        }
+    }
+
+    /// Publishes again the publications and sync requests whose backoff has passed
+    fn retry_publishes(&mut self) {
+        let now = Instant::now();
+        let (due, waiting) = std::mem::take(&mut self.pending_publishes)
+            .into_iter()
+            .partition(|pending| pending.retry_at <= now);
+        self.pending_publishes = waiting;
+
+        for pending in due {
+            self.publish(pending.topic, pending.data, pending.attempts);
+        }
+
+        let (due, waiting) = std::mem::take(&mut self.pending_syncs)
+            .into_iter()
+            .partition(|pending| pending.retry_at <= now);
+        self.pending_syncs = waiting;
+
+        for pending in due {
+            self.request_sync(
+                pending.live_object_id,
+                pending.message_ids,
+                pending.attempts,
+            );
+        }
+    }
+
+    /// Sends the sync request to a random replica of the live object, only that replica
+    /// answers, retrying later if no replica is connected yet
+    fn request_sync(
+        &mut self,
+        live_object_id: [u8; 32],
+        message_ids: Vec<[u8; 32]>,
+        attempts: u32,
+    ) {
+        let topic = IdentTopic::new(live_object_topic(&live_object_id));
+        let topic_hash = topic.hash();
+
+        let replicas: Vec<PeerId> = self
+            .swarm
+            .behaviour()
+            .gossipsub
+            .all_peers()
+            .filter(|(_, topics)| topics.contains(&&topic_hash))
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        let Some(responder) = replicas.choose(&mut rand::thread_rng()) else {
+            if attempts < self.publish_max_retries {
+                let backoff = self
+                    .publish_retry_backoff
+                    .saturating_mul(1 << attempts.min(16));
+                debug!(target: "ramd::p2p", "No replicas on topic {} to sync with, retrying in {:?}", topic, backoff);
+
+                self.pending_syncs.push(PendingSync {
+                    live_object_id,
+                    message_ids,
+                    attempts: attempts + 1,
+                    retry_at: Instant::now() + backoff,
+                });
+            } else {
+                warn!(target: "ramd::p2p", "No replicas on topic {} to sync with after {} retries", topic, attempts);
+            }
+            return;
+        };
+
+        let now = Instant::now();
+        self.sync_requests
+            .retain(|_, (_, sent_at)| now.duration_since(*sent_at) < SYNC_REQUEST_TIMEOUT);
+
+        let nonce = rand::random();
+        let request = P2pMessage::SyncRequest {
+            live_object_id,
+            nonce,
+            responder: responder.to_bytes(),
+            message_ids,
+        };
+
+        match self.encode(&request) {
+            Ok(data) => {
+                debug!(target: "ramd::p2p", "Requesting sync of topic {} from peer {}", topic, responder);
+
+                self.sync_requests.insert(nonce, (live_object_id, now));
+                self.publish(topic, data, 0);
+            }
+            Err(e) => {
+                error!(target: "ramd::p2p", "Failed to serialize sync request due to: {}", e);
+            }
+        }
+    }
+
+    /// Starts receiving gossip of the live object
+    fn subscribe(&mut self, live_object_id: [u8; 32]) {
+        let topic = IdentTopic::new(live_object_topic(&live_object_id));
+
+        match self.swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+            Ok(_) => {
+                info!(target: "ramd::p2p", "Subscribed to topic {}", topic);
+                self.topics.insert(topic.hash(), live_object_id);
+            }
+            Err(e) => {
+                error!(target: "ramd::p2p", "Failed to subscribe to topic {} due to: {}", topic, e.to_string());
+            }
+        }
+    }
+
+    /// Stops receiving gossip of the live object
+    fn unsubscribe(&mut self, live_object_id: [u8; 32]) {
+        let topic = IdentTopic::new(live_object_topic(&live_object_id));
+
+        match self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic) {
+            Ok(_) => {
+                info!(target: "ramd::p2p", "Unsubscribed from topic {}", topic);
+                self.topics.remove(&topic.hash());
+            }
+            Err(e) => {
+                error!(target: "ramd::p2p", "Failed to unsubscribe from topic {} due to: {}", topic, e.to_string());
+            }
+        }
+    }
+
     /// Checks does peer id is one of the boot nodes from the config
     fn is_boot_node(&self, peer_id: &PeerId) -> bool {
         self.boot_nodes.iter().any(|peer| peer == peer_id)
@@ -310,3 +683,215 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ramd_db::memory::MemoryStorage;
+    use ramd_p2p_types::{codec::BINCODE_TAG, message::P2P_PROTOCOL_VERSION};
+
+    const LIVE_OBJECT: [u8; 32] = [1; 32];
+
+    /// Validator standing in for the node, accepting or rejecting every message
+    struct StubValidator(bool);
+
+    impl P2pValidator for StubValidator {
+        fn validate(&self, _: &P2pMessage) -> Result<(), P2pMessageError> {
+            match self.0 {
+                true => Ok(()),
+                false => Err(P2pMessageError::Invalid("rejected by the node".to_owned())),
+            }
+        }
+    }
+
+    /// Server replicating [`LIVE_OBJECT`], listening on a random port
+    fn server(accept: bool) -> Server<MemoryStorage> {
+        let config = P2pConfig {
+            port: 0,
+            ..Default::default()
+        };
+        let (inbound_sender, _) = async_channel::unbounded();
+        let (mut server, _) = Server::new(
+            &config,
+            Arc::new(MemoryStorage::default()),
+            inbound_sender,
+            Box::new(StubValidator(accept)),
+        )
+        .unwrap();
+
+        server.subscribe(LIVE_OBJECT);
+        server
+    }
+
+    /// Gossip of the payload on the topic of the live object
+    fn gossip(live_object_id: &[u8; 32], data: Vec<u8>) -> gossipsub::Message {
+        gossipsub::Message {
+            source: None,
+            data,
+            sequence_number: None,
+            topic: IdentTopic::new(live_object_topic(live_object_id)).hash(),
+        }
+    }
+
+    fn new_message(live_object_id: [u8; 32]) -> P2pMessage {
+        P2pMessage::NewMessage {
+            live_object_id,
+            message: vec![1, 2, 3],
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_messages() {
+        let mut server = server(true);
+        let message = new_message(LIVE_OBJECT);
+
+        let data = server.encode(&message).unwrap();
+        assert_eq!(
+            server.validate(&gossip(&LIVE_OBJECT, data)),
+            Ok(Some(message))
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_messages_the_node_has_no_use_for() {
+        let mut server = server(true);
+
+        // topic of a live object which isn't replicated
+        let other = [2; 32];
+        let data = server.encode(&new_message(other)).unwrap();
+        assert_eq!(server.validate(&gossip(&other, data)), Ok(None));
+
+        // peers running another protocol version or encoding
+        let mut data = vec![BINCODE_TAG];
+        data.extend((P2P_PROTOCOL_VERSION + 1).to_le_bytes());
+        assert_eq!(server.validate(&gossip(&LIVE_OBJECT, data)), Ok(None));
+        assert_eq!(
+            server.validate(&gossip(&LIVE_OBJECT, vec![u8::MAX, 1, 2])),
+            Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_messages() {
+        let mut server = server(true);
+
+        let mut unknown_topic = gossip(
+            &LIVE_OBJECT,
+            server.encode(&new_message(LIVE_OBJECT)).unwrap(),
+        );
+        unknown_topic.topic = TopicHash::from_raw("ramd/other");
+        assert!(server.validate(&unknown_topic).is_err());
+
+        let malformed = gossip(&LIVE_OBJECT, vec![BINCODE_TAG, 1]);
+        assert!(server.validate(&malformed).is_err());
+
+        let mut compressed_garbage = compress(b"not a message").unwrap();
+        compressed_garbage.truncate(compressed_garbage.len() - 1);
+        assert!(server
+            .validate(&gossip(&LIVE_OBJECT, compressed_garbage))
+            .is_err());
+
+        // a message of another live object on the topic
+        let data = server.encode(&new_message([2; 32])).unwrap();
+        assert!(server.validate(&gossip(&LIVE_OBJECT, data)).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_messages_the_validator_rejects() {
+        let mut server = server(false);
+
+        let data = server.encode(&new_message(LIVE_OBJECT)).unwrap();
+        assert_eq!(
+            server.validate(&gossip(&LIVE_OBJECT, data)),
+            Err("Invalid message: rejected by the node".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_publishes_until_the_limit() {
+        let mut server = server(true);
+        server.publish_max_retries = 2;
+        server.publish_retry_backoff = Duration::ZERO;
+
+        // no peer is subscribed, every attempt fails
+        let topic = IdentTopic::new(live_object_topic(&LIVE_OBJECT));
+        server.publish(topic, vec![1, 2, 3], 0);
+        assert_eq!(server.pending_publishes.len(), 1);
+
+        server.retry_publishes();
+        assert_eq!(server.pending_publishes[0].attempts, 2);
+
+        // the publication is dropped once the retries are used up
+        server.retry_publishes();
+        assert!(server.pending_publishes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ignores_sync_requests_for_other_replicas() {
+        let mut server = server(true);
+        let request = |responder: Vec<u8>| P2pMessage::SyncRequest {
+            live_object_id: LIVE_OBJECT,
+            nonce: 1,
+            responder,
+            message_ids: vec![[2; 32]],
+        };
+
+        let other = server
+            .encode(&request(PeerId::random().to_bytes()))
+            .unwrap();
+        assert_eq!(server.validate(&gossip(&LIVE_OBJECT, other)), Ok(None));
+
+        let own = request(server.swarm.local_peer_id().to_bytes());
+        let data = server.encode(&own).unwrap();
+        assert_eq!(server.validate(&gossip(&LIVE_OBJECT, data)), Ok(Some(own)));
+    }
+
+    #[tokio::test]
+    async fn accepts_only_responses_to_own_sync_requests() {
+        let mut server = server(true);
+        let other = [2; 32];
+        server.subscribe(other);
+        server
+            .sync_requests
+            .insert(1, (LIVE_OBJECT, Instant::now()));
+        server.sync_requests.insert(2, (other, Instant::now()));
+
+        let heads = |nonce: u64| P2pMessage::DagHeads {
+            live_object_id: LIVE_OBJECT,
+            nonce,
+            heads: vec![[3; 32]],
+        };
+        let mut validate = |message: &P2pMessage| {
+            let data = server.encode(message).unwrap();
+            server.validate(&gossip(&LIVE_OBJECT, data))
+        };
+
+        // unknown nonce, or the nonce of a request for another live object
+        assert_eq!(validate(&heads(3)), Ok(None));
+        assert_eq!(validate(&heads(2)), Ok(None));
+
+        // a request is answered once, later responses are ignored
+        assert_eq!(validate(&heads(1)), Ok(Some(heads(1))));
+        assert_eq!(validate(&heads(1)), Ok(None));
+        assert_eq!(server.sync_requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_sync_messages_past_the_limits() {
+        let mut server = server(true);
+        server
+            .sync_requests
+            .insert(1, (LIVE_OBJECT, Instant::now()));
+
+        let heads = P2pMessage::DagHeads {
+            live_object_id: LIVE_OBJECT,
+            nonce: 1,
+            heads: vec![[3; 32]; MAX_SYNC_MESSAGE_IDS + 1],
+        };
+        let data = server.encode(&heads).unwrap();
+        assert!(server.validate(&gossip(&LIVE_OBJECT, data)).is_err());
+
+        // the request is still waiting for a valid response
+        assert!(server.sync_requests.contains_key(&1));
+    }
+}
@@ -0,0 +1,239 @@
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+use crate::message::{P2pMessage, P2pMessageError, P2P_PROTOCOL_VERSION};
+
+/// Tag of payloads encoded by [`BincodeCodec`]
+pub const BINCODE_TAG: u8 = 0;
+/// Tag of payloads encoded by [`JsonCodec`]
+pub const JSON_TAG: u8 = 1;
+
+/// Wire encoding of p2p messages. Payloads start with the tag of their codec, so nodes decode
+/// messages of peers sending with another codec.
+pub trait P2pCodec: Send + Sync {
+    /// First byte of every payload encoded by this codec
+    fn tag(&self) -> u8;
+
+    /// Encodes the message tagged with the codec and the current protocol version
+    fn encode(&self, message: &P2pMessage) -> Result<Vec<u8>, P2pMessageError>;
+
+    /// Decodes a message, messages of other protocol versions are rejected without decoding
+    /// them since their layout may differ
+    fn decode(&self, data: &[u8]) -> Result<P2pMessage, P2pMessageError>;
+}
+
+/// Decodes a payload with the codec its tag names
+pub fn decode_any(data: &[u8]) -> Result<P2pMessage, P2pMessageError> {
+    match data.first() {
+        Some(&BINCODE_TAG) => BincodeCodec.decode(data),
+        Some(&JSON_TAG) => JsonCodec.decode(data),
+        Some(&tag) => Err(P2pMessageError::UnsupportedEncoding(tag)),
+        None => Err(P2pMessageError::Malformed("empty payload".to_string())),
+    }
+}
+
+/// Wire format of a message, the version is checked before the message is decoded
+#[derive(Deserialize, Serialize)]
+struct Envelope<M> {
+    version: u16,
+    message: M,
+}
+
+/// Strips the codec tag off the payload
+fn untag(data: &[u8], tag: u8) -> Result<&[u8], P2pMessageError> {
+    match data.split_first() {
+        Some((&first, rest)) if first == tag => Ok(rest),
+        Some((&first, _)) => Err(P2pMessageError::UnsupportedEncoding(first)),
+        None => Err(P2pMessageError::Malformed("empty payload".to_string())),
+    }
+}
+
+/// Compact binary encoding, byte arrays are stored as is
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+impl P2pCodec for BincodeCodec {
+    fn tag(&self) -> u8 {
+        BINCODE_TAG
+    }
+
+    fn encode(&self, message: &P2pMessage) -> Result<Vec<u8>, P2pMessageError> {
+        let envelope = Envelope {
+            version: P2P_PROTOCOL_VERSION,
+            message,
+        };
+
+        let mut data = vec![BINCODE_TAG];
+        bincode::serialize_into(&mut data, &envelope)
+            .map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+
+        Ok(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<P2pMessage, P2pMessageError> {
+        let data = untag(data, BINCODE_TAG)?;
+
+        // the version is encoded first, so it's readable whatever follows
+        let version: u16 =
+            bincode::deserialize(data).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+        if version != P2P_PROTOCOL_VERSION {
+            return Err(P2pMessageError::UnsupportedVersion(version));
+        }
+
+        let envelope: Envelope<P2pMessage> =
+            bincode::deserialize(data).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+
+        Ok(envelope.message)
+    }
+}
+
+/// Human readable encoding, meant for debugging
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl P2pCodec for JsonCodec {
+    fn tag(&self) -> u8 {
+        JSON_TAG
+    }
+
+    fn encode(&self, message: &P2pMessage) -> Result<Vec<u8>, P2pMessageError> {
+        let envelope = Envelope {
+            version: P2P_PROTOCOL_VERSION,
+            message,
+        };
+
+        let mut data = vec![JSON_TAG];
+        serde_json::to_writer(&mut data, &envelope)
+            .map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+
+        Ok(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<P2pMessage, P2pMessageError> {
+        let data = untag(data, JSON_TAG)?;
+
+        let header: Envelope<IgnoredAny> =
+            serde_json::from_slice(data).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+        if header.version != P2P_PROTOCOL_VERSION {
+            return Err(P2pMessageError::UnsupportedVersion(header.version));
+        }
+
+        let envelope: Envelope<P2pMessage> =
+            serde_json::from_slice(data).map_err(|e| P2pMessageError::Malformed(e.to_string()))?;
+
+        Ok(envelope.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> P2pMessage {
+        P2pMessage::SyncResponse {
+            live_object_id: [7; 32],
+            nonce: 42,
+            messages: vec![vec![1, 2, 3], vec![]],
+            truncated: true,
+        }
+    }
+
+    fn codecs() -> [Box<dyn P2pCodec>; 2] {
+        [Box::new(BincodeCodec), Box::new(JsonCodec)]
+    }
+
+    /// Payload of the message wrapped in an envelope of the given version
+    fn encode_version(codec: &dyn P2pCodec, version: u16) -> Vec<u8> {
+        let envelope = Envelope {
+            version,
+            message: message(),
+        };
+
+        let mut data = vec![codec.tag()];
+        match codec.tag() {
+            BINCODE_TAG => bincode::serialize_into(&mut data, &envelope).unwrap(),
+            _ => serde_json::to_writer(&mut data, &envelope).unwrap(),
+        }
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        for codec in codecs() {
+            let data = codec.encode(&message()).unwrap();
+
+            assert_eq!(data[0], codec.tag());
+            assert_eq!(codec.decode(&data).unwrap(), message());
+            assert_eq!(decode_any(&data).unwrap(), message());
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        for codec in codecs() {
+            let data = encode_version(codec.as_ref(), P2P_PROTOCOL_VERSION + 1);
+
+            assert!(matches!(
+                codec.decode(&data),
+                Err(P2pMessageError::UnsupportedVersion(v)) if v == P2P_PROTOCOL_VERSION + 1
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions_with_another_layout() {
+        // a future version may change the message layout entirely
+        let mut data = vec![BINCODE_TAG];
+        data.extend((P2P_PROTOCOL_VERSION + 1).to_le_bytes());
+        data.extend([0xff; 3]);
+
+        assert!(matches!(
+            BincodeCodec.decode(&data),
+            Err(P2pMessageError::UnsupportedVersion(_))
+        ));
+
+        let mut data = vec![JSON_TAG];
+        data.extend(
+            format!(
+                r#"{{"version":{},"message":{{"Unknown":null}}}}"#,
+                P2P_PROTOCOL_VERSION + 1
+            )
+            .bytes(),
+        );
+
+        assert!(matches!(
+            JsonCodec.decode(&data),
+            Err(P2pMessageError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_payloads_of_other_codecs() {
+        let data = JsonCodec.encode(&message()).unwrap();
+
+        assert!(matches!(
+            BincodeCodec.decode(&data),
+            Err(P2pMessageError::UnsupportedEncoding(JSON_TAG))
+        ));
+        assert!(matches!(
+            decode_any(&[9, 1, 2]),
+            Err(P2pMessageError::UnsupportedEncoding(9))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        for codec in codecs() {
+            let mut data = codec.encode(&message()).unwrap();
+            data.truncate(data.len() / 2);
+
+            assert!(matches!(
+                codec.decode(&data),
+                Err(P2pMessageError::Malformed(_))
+            ));
+        }
+        assert!(matches!(
+            decode_any(&[]),
+            Err(P2pMessageError::Malformed(_))
+        ));
+    }
+}
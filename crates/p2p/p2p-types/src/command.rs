@@ -0,0 +1,18 @@
+use crate::message::P2pMessage;
+
+/// Requests of the node to the p2p server
+#[derive(Debug)]
+pub enum P2pCommand {
+    /// Starts receiving gossip of the live object, once the node replicates it
+    Subscribe { live_object_id: [u8; 32] },
+    /// Stops receiving gossip of the live object
+    Unsubscribe { live_object_id: [u8; 32] },
+    /// Broadcasts the message to replicas of the live object it belongs to
+    Publish { message: P2pMessage },
+    /// Asks a replica of the live object for the messages, or for its DAG heads if no message ID
+    /// is given, requests with many IDs are split
+    Sync {
+        live_object_id: [u8; 32],
+        message_ids: Vec<[u8; 32]>,
+    },
+}
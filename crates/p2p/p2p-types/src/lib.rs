@@ -1 +1,5 @@
+pub mod codec;
+pub mod command;
 pub mod message;
+pub mod topic;
+pub mod validator;
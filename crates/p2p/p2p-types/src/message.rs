@@ -1,6 +1,173 @@
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Version of the wire protocol, bumped on every incompatible change of [`P2pMessage`]
+pub const P2P_PROTOCOL_VERSION: u16 = 3;
+
+/// Largest gossip payload. Fits a create or upgrade message carrying a module of the protocol
+/// limit, `MAX_MODULE_SIZE` of 4 MiB, even json encoded where a byte takes up to four characters
+pub const MAX_TRANSMIT_SIZE: usize = 17 * 1024 * 1024;
+
+/// Largest number of message IDs, or heads, carried by a sync message
+pub const MAX_SYNC_MESSAGE_IDS: usize = 256;
+
+/// Largest total size of the messages of a sync response, a single larger message is still
+/// sent alone
+pub const MAX_SYNC_RESPONSE_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum P2pMessageError {
+    #[error("Unsupported protocol version {0}, this node speaks version {P2P_PROTOCOL_VERSION}")]
+    UnsupportedVersion(u16),
+    #[error("Unsupported encoding tag {0}")]
+    UnsupportedEncoding(u8),
+    #[error("Malformed payload: {0}")]
+    Malformed(String),
+    #[error("Invalid message: {0}")]
+    Invalid(String),
+}
+
+/// Messages exchanged between replicas of a live object, all of them are sent on the topic of
+/// the live object.
+///
+/// Sync requests are addressed to a single replica and answered with the nonce of the request,
+/// other replicas ignore them and don't forward them.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum P2pMessage {
-    Noop { data: String },
+    /// Message of a live object accepted by a replica, bincode encoded
+    NewMessage {
+        live_object_id: [u8; 32],
+        message: Vec<u8>,
+    },
+    /// New code of the live object was deployed, replicas missing the version should sync
+    ModuleAnnouncement {
+        live_object_id: [u8; 32],
+        version: u64,
+        module_hash: [u8; 32],
+    },
+    /// Latest applied messages of the live object on the responding replica
+    DagHeads {
+        live_object_id: [u8; 32],
+        nonce: u64,
+        heads: Vec<[u8; 32]>,
+    },
+    /// Asks the responder for the messages, or for its DAG heads if no message ID is given
+    SyncRequest {
+        live_object_id: [u8; 32],
+        /// Distinguishes repeated requests and pairs them with their response
+        nonce: u64,
+        /// Peer ID of the replica asked
+        responder: Vec<u8>,
+        message_ids: Vec<[u8; 32]>,
+    },
+    /// Requested messages known to the responding replica, bincode encoded
+    SyncResponse {
+        live_object_id: [u8; 32],
+        nonce: u64,
+        messages: Vec<Vec<u8>>,
+        /// Whether requested messages were left out to keep the response small
+        truncated: bool,
+    },
+}
+
+impl P2pMessage {
+    /// Live object the message belongs to
+    pub fn live_object_id(&self) -> &[u8; 32] {
+        match self {
+            P2pMessage::NewMessage { live_object_id, .. }
+            | P2pMessage::ModuleAnnouncement { live_object_id, .. }
+            | P2pMessage::DagHeads { live_object_id, .. }
+            | P2pMessage::SyncRequest { live_object_id, .. }
+            | P2pMessage::SyncResponse { live_object_id, .. } => live_object_id,
+        }
+    }
+
+    /// Checks the sync limits, larger messages are never sent by well-behaved replicas
+    pub fn check_limits(&self) -> Result<(), P2pMessageError> {
+        match self {
+            P2pMessage::DagHeads { heads: ids, .. }
+            | P2pMessage::SyncRequest {
+                message_ids: ids, ..
+            } if ids.len() > MAX_SYNC_MESSAGE_IDS => Err(P2pMessageError::Invalid(format!(
+                "{} message IDs exceed the limit of {}",
+                ids.len(),
+                MAX_SYNC_MESSAGE_IDS
+            ))),
+            P2pMessage::SyncResponse { messages, .. } => {
+                let len: usize = messages.iter().map(Vec::len).sum();
+
+                if messages.len() > MAX_SYNC_MESSAGE_IDS
+                    || (messages.len() > 1 && len > MAX_SYNC_RESPONSE_LEN)
+                {
+                    return Err(P2pMessageError::Invalid(format!(
+                        "response of {} messages and {} bytes exceeds the limits",
+                        messages.len(),
+                        len
+                    )));
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sync_request(message_ids: usize) -> P2pMessage {
+        P2pMessage::SyncRequest {
+            live_object_id: [1; 32],
+            nonce: 1,
+            responder: vec![2],
+            message_ids: vec![[3; 32]; message_ids],
+        }
+    }
+
+    fn sync_response(messages: Vec<Vec<u8>>) -> P2pMessage {
+        P2pMessage::SyncResponse {
+            live_object_id: [1; 32],
+            nonce: 1,
+            messages,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn limits_the_number_of_message_ids() {
+        assert!(sync_request(MAX_SYNC_MESSAGE_IDS).check_limits().is_ok());
+        assert!(matches!(
+            sync_request(MAX_SYNC_MESSAGE_IDS + 1).check_limits(),
+            Err(P2pMessageError::Invalid(_))
+        ));
+
+        let heads = |count| P2pMessage::DagHeads {
+            live_object_id: [1; 32],
+            nonce: 1,
+            heads: vec![[3; 32]; count],
+        };
+        assert!(heads(MAX_SYNC_MESSAGE_IDS).check_limits().is_ok());
+        assert!(heads(MAX_SYNC_MESSAGE_IDS + 1).check_limits().is_err());
+
+        let messages = vec![vec![4]; MAX_SYNC_MESSAGE_IDS + 1];
+        assert!(sync_response(messages).check_limits().is_err());
+    }
+
+    #[test]
+    fn limits_the_size_of_sync_responses() {
+        let half = vec![4; MAX_SYNC_RESPONSE_LEN / 2];
+        assert!(sync_response(vec![half.clone(), half.clone()])
+            .check_limits()
+            .is_ok());
+        assert!(sync_response(vec![half.clone(), half, vec![4]])
+            .check_limits()
+            .is_err());
+
+        // a single larger message is still sent alone
+        let large = vec![4; MAX_SYNC_RESPONSE_LEN + 1];
+        assert!(sync_response(vec![large.clone()]).check_limits().is_ok());
+        assert!(sync_response(vec![large, vec![4]]).check_limits().is_err());
+    }
 }
@@ -0,0 +1,18 @@
+/// Prefix of gossipsub topics, followed by the hex encoded live object ID
+pub const LIVE_OBJECT_TOPIC_PREFIX: &str = "ramd/lo/";
+
+/// Returns the gossipsub topic carrying messages of the live object
+pub fn live_object_topic(live_object_id: &[u8; 32]) -> String {
+    format!(
+        "{}{}",
+        LIVE_OBJECT_TOPIC_PREFIX,
+        hex::encode(live_object_id)
+    )
+}
+
+/// Returns the live object ID of the topic, `None` if it isn't a live object topic
+pub fn topic_live_object_id(topic: &str) -> Option<[u8; 32]> {
+    let id = topic.strip_prefix(LIVE_OBJECT_TOPIC_PREFIX)?;
+
+    hex::decode(id).ok()?.try_into().ok()
+}
@@ -0,0 +1,8 @@
+use crate::message::{P2pMessage, P2pMessageError};
+
+/// Checks the content of received messages before they are forwarded to other peers,
+/// implemented by the node which knows how to interpret live object messages
+pub trait P2pValidator: Send + Sync {
+    /// Returns an error if the message is invalid, its sender is penalized then
+    fn validate(&self, message: &P2pMessage) -> Result<(), P2pMessageError>;
+}
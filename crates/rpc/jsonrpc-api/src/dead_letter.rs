@@ -0,0 +1,21 @@
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use ramd_jsonrpc_types::{
+    dead_letter::{DeadLetter, PurgeDeadLetters, RetryDeadLetter},
+    live_object::Receipt,
+};
+
+#[rpc(server, client, namespace = "dead_letter")]
+pub trait DeadLetterApi {
+    /// Returns messages quarantined after they failed to be processed
+    #[method(name = "list")]
+    async fn dead_letters(&self) -> RpcResult<Vec<DeadLetter>>;
+
+    /// Processes the quarantined message again, returns its receipt or `null` if it's still
+    /// waiting for predecessors
+    #[method(name = "retry")]
+    async fn retry_dead_letter(&self, request: RetryDeadLetter) -> RpcResult<Option<Receipt>>;
+
+    /// Returns the number of removed dead letters
+    #[method(name = "purge")]
+    async fn purge_dead_letters(&self, request: PurgeDeadLetters) -> RpcResult<usize>;
+}
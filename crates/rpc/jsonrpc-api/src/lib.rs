@@ -1,9 +1,12 @@
+mod dead_letter;
 mod live_object;
 
 pub mod server {
+    pub use crate::dead_letter::DeadLetterApiServer;
     pub use crate::live_object::LiveObjectApiServer;
 }
 
 pub mod client {
+    pub use crate::dead_letter::DeadLetterApiClient;
     pub use crate::live_object::LiveObjectApiClient;
 }
@@ -1,8 +1,56 @@
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
-use ramd_jsonrpc_types::live_object::CreateLiveObject;
+use ramd_jsonrpc_types::live_object::{
+    CreateLiveObject, ExecuteLiveObject, GetHeads, GetReceipt, GrantRole, QueryLiveObject, Receipt,
+    ReplicateLiveObject, RevokeRole, SubmitMessage, UpgradeLiveObject,
+};
 
 #[rpc(server, client, namespace = "live_object")]
 pub trait LiveObjectApi {
     #[method(name = "create")]
-    async fn create_live_object(&self, request: CreateLiveObject) -> RpcResult<()>;
+    /// Returns hex encoded ID of the created live object
+    async fn create_live_object(&self, request: CreateLiveObject) -> RpcResult<String>;
+
+    /// Returns hex encoded ID of the message calling the live object method
+    #[method(name = "execute")]
+    async fn execute_live_object(&self, request: ExecuteLiveObject) -> RpcResult<String>;
+
+    /// Returns hex encoded ID of the message upgrading the live object code
+    #[method(name = "upgrade")]
+    async fn upgrade_live_object(&self, request: UpgradeLiveObject) -> RpcResult<String>;
+
+    /// Returns hex encoded ID of the message granting the role
+    #[method(name = "grant_role")]
+    async fn grant_role(&self, request: GrantRole) -> RpcResult<String>;
+
+    /// Returns hex encoded ID of the message revoking the role
+    #[method(name = "revoke_role")]
+    async fn revoke_role(&self, request: RevokeRole) -> RpcResult<String>;
+
+    /// Returns hex encoded ID of the message signed by the client, it's broadcast once applied
+    #[method(name = "submit")]
+    async fn submit_message(&self, request: SubmitMessage) -> RpcResult<String>;
+
+    /// Returns hex encoded IDs of the live object heads, predecessors of the next message
+    #[method(name = "heads")]
+    async fn heads(&self, request: GetHeads) -> RpcResult<Vec<String>>;
+
+    /// Returns base64 encoded result of the read-only live object method, state is left intact
+    #[method(name = "query")]
+    async fn query_live_object(&self, request: QueryLiveObject) -> RpcResult<String>;
+
+    /// Returns the receipt of the message, `null` if it wasn't processed yet
+    #[method(name = "receipt")]
+    async fn receipt(&self, request: GetReceipt) -> RpcResult<Option<Receipt>>;
+
+    /// Starts replicating the live object, its messages are fetched from other replicas
+    #[method(name = "replicate")]
+    async fn replicate(&self, request: ReplicateLiveObject) -> RpcResult<()>;
+
+    /// Stops replicating the live object, its state stays available for queries
+    #[method(name = "stop_replicating")]
+    async fn stop_replicating(&self, request: ReplicateLiveObject) -> RpcResult<()>;
+
+    /// Returns hex encoded IDs of the live objects replicated by the node
+    #[method(name = "replicated")]
+    async fn replicated(&self) -> RpcResult<Vec<String>>;
 }
@@ -5,8 +5,8 @@ pub use jsonrpsee::server::ServerBuilder;
 use jsonrpsee::{server::ServerHandle, RpcModule};
 use ramd_config::configs::rpc::JsonRpcServerConfig;
 use ramd_db::storage::Storage;
-use ramd_jsonrpc::live_object::LiveObjectApi;
-use ramd_jsonrpc_api::server::LiveObjectApiServer;
+use ramd_jsonrpc::{dead_letter::DeadLetterApi, live_object::LiveObjectApi};
+use ramd_jsonrpc_api::server::{DeadLetterApiServer, LiveObjectApiServer};
 use ramd_node::Node;
 use tracing::info;
 
@@ -20,11 +20,16 @@ where
 {
     let mut module = RpcModule::new(());
 
-    let live_object_api = LiveObjectApi::new(node.clone());
+    let live_object_api = LiveObjectApi::new(node.clone(), config.allow_node_signing);
     module
         .merge(live_object_api.into_rpc())
         .map_err(|_| eyre::eyre!("Live object API has conflicting methods"))?;
 
+    let dead_letter_api = DeadLetterApi::new(node.clone());
+    module
+        .merge(dead_letter_api.into_rpc())
+        .map_err(|_| eyre::eyre!("Dead letter API has conflicting methods"))?;
+
     let socket_addr = format!("0.0.0.0:{}", config.port).parse::<SocketAddr>()?;
     let server = ServerBuilder::new()
         .build(socket_addr)
@@ -0,0 +1,40 @@
+use jsonrpsee::core::RpcResult;
+use serde::{Deserialize, Serialize};
+
+use crate::live_object::decode_message_id;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub message_id: String,     // Hex encoded message ID.
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub error: String,          // Error of the latest failure.
+    pub failed_at: u64,         // Unix time in seconds of the latest failure.
+    pub retries: u32,
+    pub transient: bool, // Whether the message is retried automatically.
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryDeadLetter {
+    pub message_id: String, // Hex encoded message ID.
+}
+
+impl RetryDeadLetter {
+    pub fn decode_message_id(&self) -> RpcResult<[u8; 32]> {
+        decode_message_id(&self.message_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeDeadLetters {
+    #[serde(default)]
+    pub message_id: Option<String>, // Hex encoded message ID, all dead letters if missing.
+}
+
+impl PurgeDeadLetters {
+    pub fn decode_message_id(&self) -> RpcResult<Option<[u8; 32]>> {
+        self.message_id
+            .as_deref()
+            .map(decode_message_id)
+            .transpose()
+    }
+}
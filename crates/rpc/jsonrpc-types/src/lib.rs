@@ -1 +1,2 @@
+pub mod dead_letter;
 pub mod live_object;
@@ -11,13 +11,203 @@ pub struct CreateLiveObject {
 
 impl CreateLiveObject {
     pub fn decode_wasm_bytes(&self) -> RpcResult<Vec<u8>> {
-        match BASE64_STANDARD.decode(self.wasm_bytes.clone()) {
-            Ok(bytes) => Ok(bytes),
-            Err(e) => {
-                error!(target: "ramd::jsonrpc-types", "Failed to decode wasm bytes with error `{}`", e.to_string());
+        decode_base64(&self.wasm_bytes, "wasm bytes")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecuteLiveObject {
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub method: String,
+    pub args: String, // Base64 encoded method arguments.
+}
+
+impl ExecuteLiveObject {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+
+    pub fn decode_args(&self) -> RpcResult<Vec<u8>> {
+        decode_base64(&self.args, "args")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeLiveObject {
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub wasm_bytes: String,     // Base64 encoded wasm bytes of the new code.
+    #[serde(default)]
+    pub migrate_args: Option<String>, // Base64 encoded args of `migrate`, not called if missing.
+}
+
+impl UpgradeLiveObject {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+
+    pub fn decode_wasm_bytes(&self) -> RpcResult<Vec<u8>> {
+        decode_base64(&self.wasm_bytes, "wasm bytes")
+    }
+
+    pub fn decode_migrate_args(&self) -> RpcResult<Option<Vec<u8>>> {
+        self.migrate_args
+            .as_deref()
+            .map(|args| decode_base64(args, "migrate args"))
+            .transpose()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrantRole {
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub grantee: String,        // Hex encoded identity receiving the role.
+    pub role: String,           // One of `owner`, `writer` or `reader`.
+}
+
+impl GrantRole {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+
+    pub fn decode_grantee(&self) -> RpcResult<Vec<u8>> {
+        decode_hex(&self.grantee)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokeRole {
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub grantee: String,        // Hex encoded identity losing its role.
+}
+
+impl RevokeRole {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+
+    pub fn decode_grantee(&self) -> RpcResult<Vec<u8>> {
+        decode_hex(&self.grantee)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmitMessage {
+    pub message: String, // Base64 encoded bincode of the message signed by its author.
+}
+
+impl SubmitMessage {
+    pub fn decode_message(&self) -> RpcResult<Vec<u8>> {
+        decode_base64(&self.message, "message")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetHeads {
+    pub live_object_id: String, // Hex encoded live object ID.
+}
+
+impl GetHeads {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryLiveObject {
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub method: String,
+    pub args: String,      // Base64 encoded method arguments.
+    pub timestamp: u64,    // Unix time in seconds the query was signed at.
+    pub sender: String,    // Hex encoded public key of the caller.
+    pub signature: String, // Hex encoded signature of the sender over the query digest.
+}
+
+impl QueryLiveObject {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+
+    pub fn decode_args(&self) -> RpcResult<Vec<u8>> {
+        decode_base64(&self.args, "args")
+    }
+
+    pub fn decode_sender(&self) -> RpcResult<Vec<u8>> {
+        decode_hex(&self.sender)
+    }
+
+    pub fn decode_signature(&self) -> RpcResult<Vec<u8>> {
+        decode_hex(&self.signature)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetReceipt {
+    pub message_id: String, // Hex encoded message ID.
+}
+
+impl GetReceipt {
+    pub fn decode_message_id(&self) -> RpcResult<[u8; 32]> {
+        decode_message_id(&self.message_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicateLiveObject {
+    pub live_object_id: String, // Hex encoded live object ID.
+}
+
+impl ReplicateLiveObject {
+    pub fn decode_live_object_id(&self) -> RpcResult<[u8; 32]> {
+        decode_live_object_id(&self.live_object_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub message_id: String,     // Hex encoded message ID.
+    pub live_object_id: String, // Hex encoded live object ID.
+    pub success: bool,
+    pub error: Option<String>, // Reason the message wasn't applied.
+    pub fuel_used: u64,
+    pub keys_written: Vec<String>, // Hex encoded storage keys.
+    pub events: Vec<String>,       // Base64 encoded events.
+    pub return_bytes: String,      // Base64 encoded result of the called method.
+}
+
+fn decode_hex(value: &str) -> RpcResult<Vec<u8>> {
+    match hex::decode(value.trim_start_matches("0x")) {
+        Ok(bytes) => Ok(bytes),
+        Err(e) => {
+            error!(target: "ramd::jsonrpc-types", "Failed to decode hex value with error `{}`", e.to_string());
+
+            Err(ErrorObject::from(ErrorCode::InvalidParams))
+        }
+    }
+}
+
+pub(crate) fn decode_message_id(value: &str) -> RpcResult<[u8; 32]> {
+    decode_hex(value)?.try_into().map_err(|_| {
+        error!(target: "ramd::jsonrpc-types", "Message ID must be 32 bytes long");
+
+        ErrorObject::from(ErrorCode::InvalidParams)
+    })
+}
+
+fn decode_live_object_id(value: &str) -> RpcResult<[u8; 32]> {
+    decode_hex(value)?.try_into().map_err(|_| {
+        error!(target: "ramd::jsonrpc-types", "Live object ID must be 32 bytes long");
+
+        ErrorObject::from(ErrorCode::InvalidParams)
+    })
+}
+
+fn decode_base64(value: &str, name: &str) -> RpcResult<Vec<u8>> {
+    match BASE64_STANDARD.decode(value) {
+        Ok(bytes) => Ok(bytes),
+        Err(e) => {
+            error!(target: "ramd::jsonrpc-types", "Failed to decode {} with error `{}`", name, e.to_string());
 
-                Err(ErrorObject::from(ErrorCode::InvalidParams))
-            }
+            Err(ErrorObject::from(ErrorCode::InvalidParams))
         }
     }
 }
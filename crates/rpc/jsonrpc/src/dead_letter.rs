@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use jsonrpsee::core::RpcResult;
+use ramd_jsonrpc_api::server::DeadLetterApiServer;
+use ramd_jsonrpc_types::{
+    dead_letter::{DeadLetter, PurgeDeadLetters, RetryDeadLetter},
+    live_object::Receipt,
+};
+use ramd_node::DeadLetterHandler;
+use ramd_processor::ProcessorError;
+use tracing::info;
+
+use crate::live_object::{blocking, internal_error, invalid_params, to_rpc_receipt};
+
+pub struct DeadLetterApi<H>
+where
+    H: DeadLetterHandler,
+{
+    node: Arc<H>,
+}
+
+impl<H> DeadLetterApi<H>
+where
+    H: DeadLetterHandler,
+{
+    pub fn new(node: Arc<H>) -> Self {
+        Self { node }
+    }
+}
+
+#[async_trait]
+impl<H> DeadLetterApiServer for DeadLetterApi<H>
+where
+    H: DeadLetterHandler + 'static,
+{
+    async fn dead_letters(&self) -> RpcResult<Vec<DeadLetter>> {
+        info!(target: "ramd::jsonrpc", "Request for dead letters");
+
+        let dead_letters = blocking(&self.node, |node| node.dead_letters())
+            .await?
+            .map_err(internal_error)?;
+
+        Ok(dead_letters
+            .into_iter()
+            .map(|dead_letter| DeadLetter {
+                message_id: dead_letter.message.id_hex(),
+                live_object_id: hex::encode(dead_letter.message.action.live_object_id()),
+                error: dead_letter.error,
+                failed_at: dead_letter.failed_at,
+                retries: dead_letter.retries,
+                transient: dead_letter.transient,
+            })
+            .collect())
+    }
+
+    async fn retry_dead_letter(&self, request: RetryDeadLetter) -> RpcResult<Option<Receipt>> {
+        info!(target: "ramd::jsonrpc", "Request to retry dead letter {}", request.message_id);
+
+        let message_id = request.decode_message_id()?;
+        let receipt = blocking(&self.node, move |node| node.retry_dead_letter(message_id))
+            .await?
+            .map_err(|e| match e.downcast_ref::<ProcessorError>() {
+                Some(ProcessorError::DeadLetterNotFound(_)) => invalid_params(e),
+                _ => internal_error(e),
+            })?;
+
+        Ok(receipt.map(to_rpc_receipt))
+    }
+
+    async fn purge_dead_letters(&self, request: PurgeDeadLetters) -> RpcResult<usize> {
+        info!(target: "ramd::jsonrpc", "Request to purge dead letters {:?}", request.message_id);
+
+        let message_id = request.decode_message_id()?;
+        blocking(&self.node, move |node| node.purge_dead_letters(message_id))
+            .await?
+            .map_err(internal_error)
+    }
+}
@@ -1,25 +1,48 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use base64::prelude::{Engine, BASE64_STANDARD};
 use jsonrpsee::core::RpcResult;
+use jsonrpsee::types::{error::ErrorObjectOwned, ErrorCode};
 use ramd_jsonrpc_api::server::LiveObjectApiServer;
-use ramd_jsonrpc_types::live_object::CreateLiveObject;
+use ramd_jsonrpc_types::live_object::{
+    CreateLiveObject, ExecuteLiveObject, GetHeads, GetReceipt, GrantRole, QueryLiveObject, Receipt,
+    ReplicateLiveObject, RevokeRole, SubmitMessage, UpgradeLiveObject,
+};
 use ramd_node::LiveObjectHandler;
-use tracing::info;
+use ramd_processor::{Message, ProcessorError, Query, Role};
+use tracing::{error, info};
 
 pub struct LiveObjectApi<H>
 where
     H: LiveObjectHandler,
 {
     node: Arc<H>,
+    /// Whether callers may have messages signed with the node account, anyone reaching the
+    /// RPC port then acts as the node
+    allow_node_signing: bool,
 }
 
 impl<H> LiveObjectApi<H>
 where
     H: LiveObjectHandler,
 {
-    pub fn new(node: Arc<H>) -> Self {
-        Self { node: node.clone() }
+    pub fn new(node: Arc<H>, allow_node_signing: bool) -> Self {
+        Self {
+            node: node.clone(),
+            allow_node_signing,
+        }
+    }
+
+    /// Rejects requests for messages signed with the node account unless they are enabled
+    fn check_node_signing(&self) -> RpcResult<()> {
+        if self.allow_node_signing {
+            return Ok(());
+        }
+
+        Err(invalid_params(eyre::eyre!(
+            "Messages signed by the node account are disabled, submit messages signed by the client"
+        )))
     }
 }
 
@@ -28,11 +51,226 @@ impl<H> LiveObjectApiServer for LiveObjectApi<H>
 where
     H: LiveObjectHandler + 'static,
 {
-    async fn create_live_object(&self, request: CreateLiveObject) -> RpcResult<()> {
+    async fn create_live_object(&self, request: CreateLiveObject) -> RpcResult<String> {
         info!(target: "ramd::jsonrpc", "Request to create a live object with wasm bytes {}", request.wasm_bytes);
 
-        self.node.create_live_object(request.decode_wasm_bytes()?);
+        self.check_node_signing()?;
+
+        let wasm_bytes = request.decode_wasm_bytes()?;
+        let live_object_id = blocking(&self.node, move |node| node.create_live_object(wasm_bytes))
+            .await?
+            .map_err(|e| match e.downcast_ref::<ProcessorError>() {
+                Some(ProcessorError::InvalidModule(_)) => invalid_params(e),
+                _ => internal_error(e),
+            })?;
+
+        Ok(hex::encode(live_object_id))
+    }
+
+    async fn execute_live_object(&self, request: ExecuteLiveObject) -> RpcResult<String> {
+        info!(target: "ramd::jsonrpc", "Request to execute method `{}` of live object {}", request.method, request.live_object_id);
+
+        self.check_node_signing()?;
+
+        let live_object_id = request.decode_live_object_id()?;
+        let args = request.decode_args()?;
+        let message_id = blocking(&self.node, move |node| {
+            node.execute_live_object(live_object_id, request.method, args)
+        })
+        .await?
+        .map_err(internal_error)?;
+
+        Ok(hex::encode(message_id))
+    }
+
+    async fn upgrade_live_object(&self, request: UpgradeLiveObject) -> RpcResult<String> {
+        info!(target: "ramd::jsonrpc", "Request to upgrade live object {}", request.live_object_id);
+
+        self.check_node_signing()?;
+
+        let live_object_id = request.decode_live_object_id()?;
+        let wasm_bytes = request.decode_wasm_bytes()?;
+        let migrate_args = request.decode_migrate_args()?;
+        let message_id = blocking(&self.node, move |node| {
+            node.upgrade_live_object(live_object_id, wasm_bytes, migrate_args)
+        })
+        .await?
+        .map_err(|e| match e.downcast_ref::<ProcessorError>() {
+            Some(ProcessorError::InvalidModule(_)) => invalid_params(e),
+            _ => internal_error(e),
+        })?;
+
+        Ok(hex::encode(message_id))
+    }
+
+    async fn grant_role(&self, request: GrantRole) -> RpcResult<String> {
+        info!(target: "ramd::jsonrpc", "Request to grant role {} on live object {}", request.role, request.live_object_id);
+
+        self.check_node_signing()?;
+
+        let role: Role = request.role.parse().map_err(invalid_params)?;
+        let live_object_id = request.decode_live_object_id()?;
+        let grantee = request.decode_grantee()?;
+        let message_id = blocking(&self.node, move |node| {
+            node.grant_role(live_object_id, grantee, role)
+        })
+        .await?
+        .map_err(internal_error)?;
+
+        Ok(hex::encode(message_id))
+    }
+
+    async fn revoke_role(&self, request: RevokeRole) -> RpcResult<String> {
+        info!(target: "ramd::jsonrpc", "Request to revoke role on live object {}", request.live_object_id);
+
+        self.check_node_signing()?;
+
+        let live_object_id = request.decode_live_object_id()?;
+        let grantee = request.decode_grantee()?;
+        let message_id = blocking(&self.node, move |node| {
+            node.revoke_role(live_object_id, grantee)
+        })
+        .await?
+        .map_err(internal_error)?;
+
+        Ok(hex::encode(message_id))
+    }
+
+    async fn submit_message(&self, request: SubmitMessage) -> RpcResult<String> {
+        let message: Message = bincode::deserialize(&request.decode_message()?)
+            .map_err(|e| invalid_params(e.into()))?;
+        info!(target: "ramd::jsonrpc", "Request to submit message {} of live object {}", message.id_hex(), hex::encode(message.action.live_object_id()));
+
+        let message_id = blocking(&self.node, move |node| node.submit_message(message))
+            .await?
+            .map_err(|e| match e.downcast_ref::<ProcessorError>() {
+                Some(ProcessorError::InvalidMessage(..) | ProcessorError::InvalidModule(_)) => {
+                    invalid_params(e)
+                }
+                _ => internal_error(e),
+            })?;
+
+        Ok(hex::encode(message_id))
+    }
+
+    async fn heads(&self, request: GetHeads) -> RpcResult<Vec<String>> {
+        info!(target: "ramd::jsonrpc", "Request for heads of live object {}", request.live_object_id);
+
+        let live_object_id = request.decode_live_object_id()?;
+        let heads = blocking(&self.node, move |node| {
+            node.live_object_heads(live_object_id)
+        })
+        .await?
+        .map_err(internal_error)?;
 
-        Ok(())
+        Ok(heads.iter().map(hex::encode).collect())
     }
+
+    async fn query_live_object(&self, request: QueryLiveObject) -> RpcResult<String> {
+        info!(target: "ramd::jsonrpc", "Request to query method `{}` of live object {}", request.method, request.live_object_id);
+
+        let query = Query {
+            live_object_id: request.decode_live_object_id()?,
+            method: request.method.clone(),
+            args: request.decode_args()?,
+            timestamp: request.timestamp,
+            sender: request.decode_sender()?,
+            signature: request.decode_signature()?,
+        };
+        let result = blocking(&self.node, move |node| node.query_live_object(query))
+            .await?
+            .map_err(|e| match e.downcast_ref::<ProcessorError>() {
+                Some(ProcessorError::LiveObjectNotFound(_) | ProcessorError::Unauthorized(_)) => {
+                    invalid_params(e)
+                }
+                _ => internal_error(e),
+            })?;
+
+        Ok(BASE64_STANDARD.encode(result))
+    }
+
+    async fn receipt(&self, request: GetReceipt) -> RpcResult<Option<Receipt>> {
+        info!(target: "ramd::jsonrpc", "Request for receipt of message {}", request.message_id);
+
+        let message_id = request.decode_message_id()?;
+        let receipt = blocking(&self.node, move |node| node.message_receipt(message_id))
+            .await?
+            .map_err(internal_error)?;
+
+        Ok(receipt.map(to_rpc_receipt))
+    }
+
+    async fn replicate(&self, request: ReplicateLiveObject) -> RpcResult<()> {
+        info!(target: "ramd::jsonrpc", "Request to replicate live object {}", request.live_object_id);
+
+        let live_object_id = request.decode_live_object_id()?;
+        blocking(&self.node, move |node| {
+            node.replicate_live_object(live_object_id)
+        })
+        .await?
+        .map_err(internal_error)
+    }
+
+    async fn stop_replicating(&self, request: ReplicateLiveObject) -> RpcResult<()> {
+        info!(target: "ramd::jsonrpc", "Request to stop replicating live object {}", request.live_object_id);
+
+        let live_object_id = request.decode_live_object_id()?;
+        blocking(&self.node, move |node| {
+            node.stop_replicating_live_object(live_object_id)
+        })
+        .await?
+        .map_err(internal_error)
+    }
+
+    async fn replicated(&self) -> RpcResult<Vec<String>> {
+        let live_objects = blocking(&self.node, |node| node.replicated_live_objects())
+            .await?
+            .map_err(internal_error)?;
+
+        Ok(live_objects.iter().map(hex::encode).collect())
+    }
+}
+
+pub(crate) fn to_rpc_receipt(receipt: ramd_processor::Receipt) -> Receipt {
+    Receipt {
+        message_id: hex::encode(receipt.message_id),
+        live_object_id: hex::encode(receipt.live_object_id),
+        success: receipt.is_success(),
+        error: receipt.error,
+        fuel_used: receipt.fuel_used,
+        keys_written: receipt.keys_written.iter().map(hex::encode).collect(),
+        events: receipt
+            .events
+            .iter()
+            .map(|event| BASE64_STANDARD.encode(event))
+            .collect(),
+        return_bytes: BASE64_STANDARD.encode(receipt.return_bytes),
+    }
+}
+
+/// Runs the node call on the blocking thread pool, node calls execute wasm and hit storage
+/// which would stall the RPC runtime
+pub(crate) async fn blocking<H, T, F>(node: &Arc<H>, call: F) -> RpcResult<eyre::Result<T>>
+where
+    H: Send + Sync + 'static,
+    F: FnOnce(&H) -> eyre::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let node = node.clone();
+
+    tokio::task::spawn_blocking(move || call(&node))
+        .await
+        .map_err(|e| internal_error(e.into()))
+}
+
+pub(crate) fn invalid_params(e: eyre::Report) -> ErrorObjectOwned {
+    info!(target: "ramd::jsonrpc", "Rejected request with error `{}`", e.to_string());
+
+    ErrorObjectOwned::owned(ErrorCode::InvalidParams.code(), e.to_string(), None::<()>)
+}
+
+pub(crate) fn internal_error(e: eyre::Report) -> ErrorObjectOwned {
+    error!(target: "ramd::jsonrpc", "Failed to handle request with error `{}`", e.to_string());
+
+    ErrorObjectOwned::owned(ErrorCode::InternalError.code(), e.to_string(), None::<()>)
 }
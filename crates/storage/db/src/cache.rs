@@ -0,0 +1,535 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::storage::Storage;
+
+/// Pending writes of a single savepoint, `None` marks a deleted key
+pub type WriteLayer = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Buffers writes in memory on top of the underlying storage until they are committed.
+///
+/// Reads see the buffered writes first. Savepoints can be nested, rolling back to a
+/// savepoint discards only the writes made after it was created.
+pub struct CacheStorage<S>
+where
+    S: Storage<Vec<u8>, Vec<u8>>,
+{
+    storage: Arc<S>,
+    layers: Mutex<Vec<WriteLayer>>,
+}
+
+impl<S> CacheStorage<S>
+where
+    S: Storage<Vec<u8>, Vec<u8>>,
+{
+    pub fn new(storage: Arc<S>) -> Self {
+        Self {
+            storage,
+            layers: Mutex::new(vec![WriteLayer::new()]),
+        }
+    }
+
+    /// Creates a new savepoint, returns the number of open savepoints
+    pub fn savepoint(&self) -> usize {
+        let mut layers = self.layers();
+        layers.push(WriteLayer::new());
+
+        layers.len() - 1
+    }
+
+    /// Keeps writes made since the latest savepoint and closes it
+    pub fn release_savepoint(&self) -> eyre::Result<()> {
+        let mut layers = self.layers();
+        if layers.len() < 2 {
+            return Err(eyre::eyre!("No savepoint to release"));
+        }
+
+        let latest = layers.pop().unwrap_or_default();
+        if let Some(parent) = layers.last_mut() {
+            parent.extend(latest);
+        }
+
+        Ok(())
+    }
+
+    /// Discards writes made since the latest savepoint and closes it
+    pub fn rollback_to_savepoint(&self) -> eyre::Result<()> {
+        let mut layers = self.layers();
+        if layers.len() < 2 {
+            return Err(eyre::eyre!("No savepoint to roll back to"));
+        }
+
+        layers.pop();
+        Ok(())
+    }
+
+    /// Keys written or deleted since the latest savepoint was created
+    pub fn savepoint_writes(&self) -> Vec<Vec<u8>> {
+        self.layers()
+            .last()
+            .map(|layer| layer.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Values the keys written or deleted since the latest savepoint had before it was created,
+    /// `None` for keys which didn't exist
+    pub fn savepoint_previous_values(&self) -> eyre::Result<WriteLayer> {
+        let layers = self.layers();
+        let Some((latest, previous)) = layers.split_last() else {
+            return Ok(WriteLayer::new());
+        };
+
+        latest
+            .keys()
+            .map(|key| {
+                let value = match previous.iter().rev().find_map(|layer| layer.get(key)) {
+                    Some(value) => value.clone(),
+                    None => self.storage.get_opt(key.clone())?,
+                };
+                Ok((key.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Atomically writes all buffered changes to the underlying storage and clears the cache
+    pub fn commit(&self) -> eyre::Result<()> {
+        let mut layers = self.layers();
+
+        let mut batch = WriteLayer::new();
+        for layer in layers.drain(..) {
+            batch.extend(layer);
+        }
+        layers.push(WriteLayer::new());
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        self.storage.write_batch(batch.into_iter().collect())
+    }
+
+    /// Discards all buffered changes, including open savepoints
+    pub fn rollback(&self) {
+        let mut layers = self.layers();
+        layers.clear();
+        layers.push(WriteLayer::new());
+    }
+
+    /// Returns buffered value of the key, `None` if the key wasn't written
+    fn buffered(&self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        self.layers()
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get(key).cloned())
+    }
+
+    fn write(&self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        if let Some(layer) = self.layers().last_mut() {
+            layer.insert(key, value);
+        }
+    }
+
+    fn layers(&self) -> MutexGuard<'_, Vec<WriteLayer>> {
+        self.layers.lock().expect("cache lock is poisoned")
+    }
+}
+
+impl<K, V, S> Storage<K, V> for CacheStorage<S>
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+    S: Storage<Vec<u8>, Vec<u8>>,
+{
+    fn has(&self, key: K) -> eyre::Result<bool> {
+        Ok(<Self as Storage<K, V>>::get_opt(self, key)?.is_some())
+    }
+
+    fn get(&self, key: K) -> eyre::Result<Vec<u8>> {
+        <Self as Storage<K, V>>::get_opt(self, key)?.ok_or_else(|| eyre::eyre!("Key not found"))
+    }
+
+    fn get_opt(&self, key: K) -> eyre::Result<Option<Vec<u8>>> {
+        match self.buffered(key.as_ref()) {
+            Some(value) => Ok(value),
+            None => self.storage.get_opt(Vec::from(key.as_ref())),
+        }
+    }
+
+    fn set(&self, key: K, value: V) -> eyre::Result<()> {
+        self.write(key.as_ref().to_vec(), Some(value.as_ref().to_vec()));
+        Ok(())
+    }
+
+    fn delete(&self, key: K) -> eyre::Result<()> {
+        self.write(key.as_ref().to_vec(), None);
+        Ok(())
+    }
+
+    fn iter_prefix(&self, prefix: K) -> eyre::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let prefix = prefix.as_ref();
+
+        let mut entries: BTreeMap<Vec<u8>, Vec<u8>> = self
+            .storage
+            .iter_prefix(Vec::from(prefix))?
+            .into_iter()
+            .collect();
+
+        // buffered writes override stored values, from the oldest savepoint to the latest
+        for layer in self.layers().iter() {
+            for (key, value) in layer.range(Vec::from(prefix)..) {
+                if !key.starts_with(prefix) {
+                    break;
+                }
+
+                match value {
+                    Some(value) => entries.insert(key.clone(), value.clone()),
+                    None => entries.remove(key),
+                };
+            }
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    fn scan_prefix(
+        &self,
+        prefix: K,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> eyre::Result<()> {
+        let prefix = prefix.as_ref();
+
+        // buffered writes override stored values, from the oldest savepoint to the latest
+        let mut buffered = WriteLayer::new();
+        for layer in self.layers().iter() {
+            for (key, value) in layer.range(Vec::from(prefix)..) {
+                if !key.starts_with(prefix) {
+                    break;
+                }
+
+                buffered.insert(key.clone(), value.clone());
+            }
+        }
+
+        // stored entries are merged with the buffered ones as they are read
+        let mut buffered = buffered.into_iter().peekable();
+        let mut stopped = false;
+        self.storage
+            .scan_prefix(Vec::from(prefix), &mut |key, value| {
+                while let Some((buffered_key, _)) = buffered.peek() {
+                    if buffered_key.as_slice() > key {
+                        break;
+                    }
+
+                    let (buffered_key, buffered_value) = buffered.next().unwrap_or_default();
+                    let overrides = buffered_key == key;
+                    if let Some(buffered_value) = buffered_value {
+                        if !visit(&buffered_key, &buffered_value) {
+                            stopped = true;
+                            return false;
+                        }
+                    }
+                    if overrides {
+                        return true;
+                    }
+                }
+
+                stopped = !visit(key, value);
+                !stopped
+            })?;
+
+        if !stopped {
+            for (key, value) in buffered {
+                if let Some(value) = value {
+                    if !visit(&key, &value) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: Vec<(K, Option<V>)>) -> eyre::Result<()> {
+        for (key, value) in batch {
+            self.write(
+                key.as_ref().to_vec(),
+                value.map(|value| value.as_ref().to_vec()),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::memory::MemoryStorage;
+
+    /// Storage recording the size of every batch written to it
+    #[derive(Default)]
+    struct BatchLog {
+        storage: MemoryStorage,
+        batches: Mutex<Vec<usize>>,
+    }
+
+    impl Storage<Vec<u8>, Vec<u8>> for BatchLog {
+        fn has(&self, key: Vec<u8>) -> eyre::Result<bool> {
+            Storage::<_, Vec<u8>>::has(&self.storage, key)
+        }
+
+        fn get(&self, key: Vec<u8>) -> eyre::Result<Vec<u8>> {
+            Storage::<_, Vec<u8>>::get(&self.storage, key)
+        }
+
+        fn get_opt(&self, key: Vec<u8>) -> eyre::Result<Option<Vec<u8>>> {
+            Storage::<_, Vec<u8>>::get_opt(&self.storage, key)
+        }
+
+        fn set(&self, key: Vec<u8>, value: Vec<u8>) -> eyre::Result<()> {
+            self.storage.set(key, value)
+        }
+
+        fn delete(&self, key: Vec<u8>) -> eyre::Result<()> {
+            Storage::<_, Vec<u8>>::delete(&self.storage, key)
+        }
+
+        fn iter_prefix(&self, prefix: Vec<u8>) -> eyre::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            Storage::<_, Vec<u8>>::iter_prefix(&self.storage, prefix)
+        }
+
+        fn scan_prefix(
+            &self,
+            prefix: Vec<u8>,
+            visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+        ) -> eyre::Result<()> {
+            Storage::<_, Vec<u8>>::scan_prefix(&self.storage, prefix, visit)
+        }
+
+        fn write_batch(&self, batch: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> eyre::Result<()> {
+            self.batches.lock().unwrap().push(batch.len());
+            self.storage.write_batch(batch)
+        }
+    }
+
+    fn key(key: &str) -> Vec<u8> {
+        key.as_bytes().to_vec()
+    }
+
+    fn cache() -> (Arc<BatchLog>, CacheStorage<BatchLog>) {
+        let storage = Arc::new(BatchLog::default());
+        storage.set(key("stored"), key("old")).unwrap();
+
+        (storage.clone(), CacheStorage::new(storage))
+    }
+
+    fn get(cache: &CacheStorage<BatchLog>, name: &str) -> Option<Vec<u8>> {
+        Storage::<Vec<u8>, Vec<u8>>::get_opt(cache, key(name)).unwrap()
+    }
+
+    #[test]
+    fn reads_through_layers() {
+        let (_, cache) = cache();
+        assert_eq!(get(&cache, "stored"), Some(key("old")));
+
+        cache.set(key("a"), key("1")).unwrap();
+        cache.savepoint();
+        cache.set(key("stored"), key("new")).unwrap();
+        cache.savepoint();
+
+        assert_eq!(get(&cache, "a"), Some(key("1")));
+        assert_eq!(get(&cache, "stored"), Some(key("new")));
+        assert_eq!(get(&cache, "missing"), None);
+    }
+
+    #[test]
+    fn deletes_hide_lower_values() {
+        let (storage, cache) = cache();
+        cache.set(key("a"), key("1")).unwrap();
+
+        cache.savepoint();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("a")).unwrap();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("stored")).unwrap();
+
+        assert_eq!(get(&cache, "a"), None);
+        assert_eq!(get(&cache, "stored"), None);
+        assert!(!Storage::<Vec<u8>, Vec<u8>>::has(&cache, key("stored")).unwrap());
+        // nothing reaches the storage before the commit
+        assert!(storage.has(key("stored")).unwrap());
+    }
+
+    #[test]
+    fn rolls_back_only_the_latest_savepoint() {
+        let (_, cache) = cache();
+        cache.set(key("a"), key("1")).unwrap();
+
+        assert_eq!(cache.savepoint(), 1);
+        cache.set(key("b"), key("2")).unwrap();
+
+        assert_eq!(cache.savepoint(), 2);
+        cache.set(key("a"), key("3")).unwrap();
+        cache.set(key("c"), key("4")).unwrap();
+
+        cache.rollback_to_savepoint().unwrap();
+        assert_eq!(get(&cache, "a"), Some(key("1")));
+        assert_eq!(get(&cache, "b"), Some(key("2")));
+        assert_eq!(get(&cache, "c"), None);
+
+        cache.rollback_to_savepoint().unwrap();
+        assert_eq!(get(&cache, "a"), Some(key("1")));
+        assert_eq!(get(&cache, "b"), None);
+
+        // the base layer isn't a savepoint
+        assert!(cache.rollback_to_savepoint().is_err());
+    }
+
+    #[test]
+    fn releases_savepoints_into_their_parent() {
+        let (_, cache) = cache();
+
+        cache.savepoint();
+        cache.set(key("a"), key("1")).unwrap();
+        cache.savepoint();
+        cache.set(key("b"), key("2")).unwrap();
+        assert_eq!(cache.savepoint_writes(), vec![key("b")]);
+
+        cache.release_savepoint().unwrap();
+        assert_eq!(cache.savepoint_writes(), vec![key("a"), key("b")]);
+
+        // the released writes are rolled back together with the outer savepoint
+        cache.rollback_to_savepoint().unwrap();
+        assert_eq!(get(&cache, "a"), None);
+        assert_eq!(get(&cache, "b"), None);
+        assert!(cache.release_savepoint().is_err());
+    }
+
+    #[test]
+    fn reads_values_from_before_the_savepoint() {
+        let (_, cache) = cache();
+        cache.set(key("a"), key("1")).unwrap();
+        cache.savepoint();
+        cache.set(key("a"), key("2")).unwrap();
+
+        cache.savepoint();
+        cache.set(key("a"), key("3")).unwrap();
+        cache.set(key("new"), key("4")).unwrap();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("stored")).unwrap();
+
+        // the nearest lower layer wins, then the storage
+        assert_eq!(
+            Vec::from_iter(cache.savepoint_previous_values().unwrap()),
+            vec![
+                (key("a"), Some(key("2"))),
+                (key("new"), None),
+                (key("stored"), Some(key("old"))),
+            ]
+        );
+
+        cache.release_savepoint().unwrap();
+        assert_eq!(
+            Vec::from_iter(cache.savepoint_previous_values().unwrap()),
+            vec![
+                (key("a"), Some(key("1"))),
+                (key("new"), None),
+                (key("stored"), Some(key("old"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_prefix_iteration_with_the_storage() {
+        let (storage, cache) = cache();
+        storage.set(key("p/a"), key("stored a")).unwrap();
+        storage.set(key("p/b"), key("stored b")).unwrap();
+        storage.set(key("q/a"), key("other prefix")).unwrap();
+
+        cache.set(key("p/c"), key("c")).unwrap();
+        cache.savepoint();
+        cache.set(key("p/a"), key("a")).unwrap();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("p/b")).unwrap();
+        cache.savepoint();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("p/c")).unwrap();
+        cache.set(key("p/d"), key("d")).unwrap();
+
+        assert_eq!(
+            Storage::<Vec<u8>, Vec<u8>>::iter_prefix(&cache, key("p/")).unwrap(),
+            vec![(key("p/a"), key("a")), (key("p/d"), key("d"))]
+        );
+    }
+
+    #[test]
+    fn merges_prefix_scan_with_the_storage() {
+        let (storage, cache) = cache();
+        for name in ["p/a", "p/b", "p/c", "p/e"] {
+            storage.set(key(name), key("stored")).unwrap();
+        }
+
+        cache.set(key("p/0"), key("first")).unwrap();
+        cache.set(key("p/b"), key("b")).unwrap();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("p/c")).unwrap();
+        cache.set(key("p/d"), key("d")).unwrap();
+        cache.set(key("p/f"), key("last")).unwrap();
+
+        let scan = |limit: usize| {
+            let mut entries = Vec::new();
+            Storage::<Vec<u8>, Vec<u8>>::scan_prefix(&cache, key("p/"), &mut |key, value| {
+                entries.push((key.to_vec(), value.to_vec()));
+                entries.len() < limit
+            })
+            .unwrap();
+            entries
+        };
+
+        let all = Storage::<Vec<u8>, Vec<u8>>::iter_prefix(&cache, key("p/")).unwrap();
+        assert_eq!(scan(usize::MAX), all);
+        assert_eq!(all.len(), 6);
+
+        // stops as soon as the visitor does, wherever the entry comes from
+        for limit in 1..=all.len() {
+            assert_eq!(scan(limit), all[..limit].to_vec());
+        }
+    }
+
+    #[test]
+    fn commits_all_layers_in_one_batch() {
+        let (storage, cache) = cache();
+
+        cache.set(key("a"), key("1")).unwrap();
+        cache.savepoint();
+        cache.set(key("b"), key("2")).unwrap();
+        Storage::<Vec<u8>, Vec<u8>>::delete(&cache, key("stored")).unwrap();
+        cache.savepoint();
+        cache.set(key("a"), key("3")).unwrap();
+
+        cache.commit().unwrap();
+
+        assert_eq!(*storage.batches.lock().unwrap(), vec![3]);
+        assert_eq!(storage.get_opt(key("a")).unwrap(), Some(key("3")));
+        assert_eq!(storage.get_opt(key("b")).unwrap(), Some(key("2")));
+        assert_eq!(storage.get_opt(key("stored")).unwrap(), None);
+
+        // savepoints are closed and nothing is left to commit
+        assert!(cache.rollback_to_savepoint().is_err());
+        cache.commit().unwrap();
+        assert_eq!(storage.batches.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn discards_everything_on_rollback() {
+        let (storage, cache) = cache();
+
+        cache.set(key("a"), key("1")).unwrap();
+        cache.savepoint();
+        cache.set(key("b"), key("2")).unwrap();
+
+        cache.rollback();
+        cache.commit().unwrap();
+
+        assert_eq!(get(&cache, "a"), None);
+        assert_eq!(get(&cache, "b"), None);
+        assert!(storage.batches.lock().unwrap().is_empty());
+    }
+}
@@ -1,2 +1,173 @@
 /// Storage key used for storing p2p private key
 pub const RAMD_P2P_KEYPAIR_KEY: &[u8] = "ramd_p2p_pk".as_bytes();
+
+/// Storage key used for storing the secret key of the node account signing messages
+pub const RAMD_ACCOUNT_KEY: &[u8] = "ramd_account_sk".as_bytes();
+
+/// Storage key prefix for applied messages, followed by the message ID
+pub const RAMD_MESSAGE_PREFIX: &[u8] = "ramd_msg/".as_bytes();
+
+/// Returns storage key of an applied message
+pub fn message_key(message_id: &[u8]) -> Vec<u8> {
+    [RAMD_MESSAGE_PREFIX, message_id].concat()
+}
+
+/// Storage key prefix for message receipts, followed by the message ID
+pub const RAMD_RECEIPT_PREFIX: &[u8] = "ramd_receipt/".as_bytes();
+
+/// Returns storage key of the receipt of a processed message
+pub fn receipt_key(message_id: &[u8]) -> Vec<u8> {
+    [RAMD_RECEIPT_PREFIX, message_id].concat()
+}
+
+/// Storage key prefix for quarantined messages, followed by the message ID
+pub const RAMD_DEAD_LETTER_PREFIX: &[u8] = "ramd_dead/".as_bytes();
+
+/// Returns storage key of a message quarantined after it failed to be processed
+pub fn dead_letter_key(message_id: &[u8]) -> Vec<u8> {
+    [RAMD_DEAD_LETTER_PREFIX, message_id].concat()
+}
+
+/// Storage key prefix for messages submitted to the node and not broadcast yet, followed by
+/// the message ID
+pub const RAMD_OUTBOX_PREFIX: &[u8] = "ramd_outbox/".as_bytes();
+
+/// Returns storage key marking a submitted message to broadcast once it's applied
+pub fn outbox_key(message_id: &[u8]) -> Vec<u8> {
+    [RAMD_OUTBOX_PREFIX, message_id].concat()
+}
+
+/// Storage key prefix for live objects replicated by the node, followed by the live object ID
+pub const RAMD_REPLICATED_PREFIX: &[u8] = "ramd_replicated/".as_bytes();
+
+/// Returns storage key marking a live object as replicated by the node
+pub fn replicated_key(live_object_id: &[u8]) -> Vec<u8> {
+    [RAMD_REPLICATED_PREFIX, live_object_id].concat()
+}
+
+/// Storage key prefix for live objects, followed by the live object ID
+pub const RAMD_LIVE_OBJECT_PREFIX: &[u8] = "ramd_lo/".as_bytes();
+
+/// Returns storage key prefix under which all data of a live object is stored
+pub fn live_object_prefix(live_object_id: &[u8]) -> Vec<u8> {
+    [RAMD_LIVE_OBJECT_PREFIX, live_object_id, "/".as_bytes()].concat()
+}
+
+/// Returns storage key of live object wasm bytes
+pub fn live_object_code_key(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "code".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key prefix of the live object state namespace, followed by state keys
+pub fn live_object_state_prefix(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "state/".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key of the code of a live object version, followed by the big endian version
+pub fn live_object_code_version_key(live_object_id: &[u8], version: u64) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "code/".as_bytes(),
+        &version.to_be_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key of the version history of a live object
+pub fn live_object_versions_key(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "versions".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key of the identity which created a live object
+pub fn live_object_creator_key(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "creator".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key prefix of the roles granted on a live object, followed by identities
+pub fn live_object_acl_prefix(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "acl/".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key of the role granted to the identity on a live object
+pub fn live_object_acl_key(live_object_id: &[u8], identity: &[u8]) -> Vec<u8> {
+    [live_object_acl_prefix(live_object_id).as_slice(), identity].concat()
+}
+
+/// Returns storage key of the latest applied messages of a live object
+pub fn live_object_heads_key(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "heads".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key prefix of the depths of the messages applied to a live object,
+/// followed by message IDs
+pub fn live_object_depth_prefix(live_object_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_prefix(live_object_id).as_slice(),
+        "depth/".as_bytes(),
+    ]
+    .concat()
+}
+
+/// Returns storage key of the depth of a message in the DAG of its live object
+pub fn live_object_depth_key(live_object_id: &[u8], message_id: &[u8]) -> Vec<u8> {
+    [
+        live_object_depth_prefix(live_object_id).as_slice(),
+        message_id,
+    ]
+    .concat()
+}
+
+/// Storage key prefix for undo logs of the latest generations applied to live objects,
+/// followed by the live object ID
+pub const RAMD_UNDO_PREFIX: &[u8] = "ramd_undo/".as_bytes();
+
+/// Returns storage key prefix of the undo logs of a live object, followed by big endian depths
+pub fn undo_prefix(live_object_id: &[u8]) -> Vec<u8> {
+    [RAMD_UNDO_PREFIX, live_object_id, "/".as_bytes()].concat()
+}
+
+/// Returns storage key of the undo log of the generation of a live object at the depth
+pub fn undo_key(live_object_id: &[u8], depth: u64) -> Vec<u8> {
+    [undo_prefix(live_object_id).as_slice(), &depth.to_be_bytes()].concat()
+}
+
+/// Storage key prefix for compiled module artifacts, followed by the engine ID
+pub const RAMD_MODULE_ARTIFACT_PREFIX: &[u8] = "ramd_module/".as_bytes();
+
+/// Returns storage key prefix of artifacts compiled by the engine
+pub fn module_artifact_engine_prefix(engine_id: &[u8]) -> Vec<u8> {
+    [RAMD_MODULE_ARTIFACT_PREFIX, engine_id, "/".as_bytes()].concat()
+}
+
+/// Returns storage key of the module artifact compiled by the engine
+pub fn module_artifact_key(engine_id: &[u8], module_hash: &[u8]) -> Vec<u8> {
+    [
+        module_artifact_engine_prefix(engine_id).as_slice(),
+        module_hash,
+    ]
+    .concat()
+}
@@ -1,3 +1,5 @@
+pub mod cache;
 pub mod keys;
+pub mod memory;
 pub mod rocks;
 pub mod storage;
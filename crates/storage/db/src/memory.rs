@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::storage::Storage;
+
+/// Keeps entries in memory only, for tests and nodes which don't need to survive a restart
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    fn entries(&self) -> MutexGuard<'_, BTreeMap<Vec<u8>, Vec<u8>>> {
+        self.entries
+            .lock()
+            .expect("memory storage lock is poisoned")
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> Storage<K, V> for MemoryStorage {
+    fn has(&self, key: K) -> eyre::Result<bool> {
+        Ok(self.entries().contains_key(key.as_ref()))
+    }
+
+    fn get(&self, key: K) -> eyre::Result<Vec<u8>> {
+        self.entries()
+            .get(key.as_ref())
+            .cloned()
+            .ok_or_else(|| eyre::eyre!("Key not found"))
+    }
+
+    fn get_opt(&self, key: K) -> eyre::Result<Option<Vec<u8>>> {
+        Ok(self.entries().get(key.as_ref()).cloned())
+    }
+
+    fn set(&self, key: K, value: V) -> eyre::Result<()> {
+        self.entries()
+            .insert(key.as_ref().to_vec(), value.as_ref().to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: K) -> eyre::Result<()> {
+        self.entries().remove(key.as_ref());
+        Ok(())
+    }
+
+    fn iter_prefix(&self, prefix: K) -> eyre::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let prefix = prefix.as_ref();
+
+        Ok(self
+            .entries()
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn scan_prefix(
+        &self,
+        prefix: K,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> eyre::Result<()> {
+        let prefix = prefix.as_ref();
+
+        for (key, value) in self.entries().range(prefix.to_vec()..) {
+            if !key.starts_with(prefix) || !visit(key, value) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: Vec<(K, Option<V>)>) -> eyre::Result<()> {
+        let mut entries = self.entries();
+        for (key, value) in batch {
+            match value {
+                Some(value) => entries.insert(key.as_ref().to_vec(), value.as_ref().to_vec()),
+                None => entries.remove(key.as_ref()),
+            };
+        }
+
+        Ok(())
+    }
+}
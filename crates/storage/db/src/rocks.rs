@@ -46,4 +46,56 @@ impl<K: AsRef<[u8]>, V: AsRef<[u8]>> Storage<K, V> for RocksStorage {
         self.db.delete(key)?;
         Ok(())
     }
+
+    fn iter_prefix(&self, prefix: K) -> eyre::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let prefix = prefix.as_ref();
+        let mut entries = Vec::new();
+
+        for entry in self.db.iterator(rocksdb::IteratorMode::From(
+            prefix,
+            rocksdb::Direction::Forward,
+        )) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            entries.push((key.into_vec(), value.into_vec()));
+        }
+
+        Ok(entries)
+    }
+
+    fn scan_prefix(
+        &self,
+        prefix: K,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> eyre::Result<()> {
+        let prefix = prefix.as_ref();
+
+        for entry in self.db.iterator(rocksdb::IteratorMode::From(
+            prefix,
+            rocksdb::Direction::Forward,
+        )) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix) || !visit(&key, &value) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: Vec<(K, Option<V>)>) -> eyre::Result<()> {
+        let mut write_batch = rocksdb::WriteBatch::default();
+        for (key, value) in batch {
+            match value {
+                Some(value) => write_batch.put(key, value),
+                None => write_batch.delete(key),
+            }
+        }
+
+        self.db.write(write_batch)?;
+        Ok(())
+    }
 }
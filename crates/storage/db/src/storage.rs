@@ -8,4 +8,15 @@ where
     fn get_opt(&self, key: K) -> eyre::Result<Option<Vec<u8>>>;
     fn set(&self, key: K, value: V) -> eyre::Result<()>;
     fn delete(&self, key: K) -> eyre::Result<()>;
+    /// Returns all key-value pairs whose key starts with the prefix, ordered by key
+    fn iter_prefix(&self, prefix: K) -> eyre::Result<Vec<(Vec<u8>, Vec<u8>)>>;
+    /// Visits key-value pairs whose key starts with the prefix one at a time, ordered by key,
+    /// until the visitor returns `false`. Entries past that point aren't read.
+    fn scan_prefix(
+        &self,
+        prefix: K,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> eyre::Result<()>;
+    /// Atomically applies all writes, `None` value deletes the key
+    fn write_batch(&self, batch: Vec<(K, Option<V>)>) -> eyre::Result<()>;
 }
@@ -1,4 +1,4 @@
-use crate::commands::{BootnodeCmd, NodeCmd, RelayerCmd};
+use crate::commands::{BootnodeCmd, DeadLetterCmd, NodeCmd, RelayerCmd};
 use clap::Parser;
 
 #[derive(Debug, Parser)]
@@ -13,6 +13,9 @@ pub enum Subcommand {
     /// Runs ramd as bootnode mode, where the only functionalities are peer discovery
     Bootnode(Box<BootnodeCmd>),
 
+    /// Inspects, retries or purges dead letters of a running node
+    DeadLetter(Box<DeadLetterCmd>),
+
     /// Runs ramd node with full functionalities
     Node(Box<NodeCmd>),
 
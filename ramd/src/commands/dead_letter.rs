@@ -0,0 +1,29 @@
+use clap::{Args, Subcommand};
+
+#[derive(Clone, Debug, Args)]
+pub struct DeadLetterCmd {
+    /// Address of the jsonrpc server of a running node
+    #[clap(long, default_value = "http://127.0.0.1:1319")]
+    pub rpc_url: String,
+
+    #[command(subcommand)]
+    pub action: DeadLetterAction,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum DeadLetterAction {
+    /// Lists messages quarantined after they failed to be processed
+    List,
+
+    /// Processes the quarantined message again
+    Retry {
+        /// Hex encoded message ID
+        message_id: String,
+    },
+
+    /// Removes the quarantined message, or all of them if no ID is given
+    Purge {
+        /// Hex encoded message ID
+        message_id: Option<String>,
+    },
+}
@@ -1,7 +1,9 @@
 mod bootnode;
+mod dead_letter;
 mod node;
 mod relayer;
 
 pub use bootnode::*;
+pub use dead_letter::*;
 pub use node::*;
 pub use relayer::*;
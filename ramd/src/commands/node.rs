@@ -1,4 +1,5 @@
 use clap::Args;
+use ramd_config::configs::network::WireEncoding;
 use std::path::PathBuf;
 
 #[derive(Clone, Debug, Args)]
@@ -12,11 +13,17 @@ pub struct NodeCmd {
     #[clap(flatten)]
     pub node: NodeConfigs,
 
+    #[clap(flatten)]
+    pub processor: ProcessorConfigs,
+
     #[clap(flatten)]
     pub rpc: RpcConfigs,
 
     #[clap(flatten)]
     pub tracing: TracingConfigs,
+
+    #[clap(flatten)]
+    pub vm: VmConfigs,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -32,10 +39,22 @@ pub struct NetworkConfigs {
     #[clap(long)]
     pub network_boot_nodes: Option<Vec<String>>,
 
+    /// Gossip payloads of at least this many bytes are zstd compressed
+    #[clap(long, default_value_t = 1024)]
+    pub network_compression_threshold: usize,
+
+    /// Disables compression of gossip payloads, compressed payloads of peers are still accepted
+    #[clap(long)]
+    pub network_no_compression: bool,
+
     /// Path for network related files
     #[clap(long, default_value = "network/")]
     pub network_config_path: PathBuf,
 
+    /// Encoding of sent gossip payloads, payloads of peers are decoded whatever their encoding
+    #[clap(long, value_enum, default_value_t = WireEncoding::Bincode)]
+    pub network_encoding: WireEncoding,
+
     /// Seconds until an idle connection timeout
     #[clap(long, default_value_t = 60)]
     pub network_idle_connection_timeout: u64,
@@ -44,6 +63,10 @@ pub struct NetworkConfigs {
     #[clap(long)]
     pub network_key: Option<PathBuf>,
 
+    /// Malformed gossip payloads a peer may send before it's disconnected
+    #[clap(long, default_value_t = 3)]
+    pub network_max_invalid_messages: u32,
+
     /// Maximum number of peers allowed
     #[clap(long, default_value_t = 10)]
     pub network_max_peers_limit: usize,
@@ -51,6 +74,14 @@ pub struct NetworkConfigs {
     /// Port for libp2p
     #[clap(long, default_value_t = 1211)]
     pub network_port: u16,
+
+    /// Retries of a broadcast that found no peers on its topic
+    #[clap(long, default_value_t = 5)]
+    pub network_publish_max_retries: u32,
+
+    /// Milliseconds before the first broadcast retry, doubled with every further retry
+    #[clap(long, default_value_t = 500)]
+    pub network_publish_retry_backoff: u64,
 }
 
 fn default_ramd_dir() -> PathBuf {
@@ -68,11 +99,47 @@ pub struct NodeConfigs {
     pub ramd_dir_name: PathBuf,
 }
 
+#[derive(Clone, Debug, Args)]
+pub struct ProcessorConfigs {
+    /// Discard the whole batch of messages if any of them fails
+    #[clap(long, default_value_t = false)]
+    pub processor_atomic_batches: bool,
+
+    /// Maximum number of messages waiting in the pool for their predecessors
+    #[clap(long, default_value_t = 10_000)]
+    pub processor_pool_max_size: usize,
+
+    /// Seconds until a message waiting in the pool is evicted
+    #[clap(long, default_value_t = 600)]
+    pub processor_pool_stale_timeout: u64,
+
+    /// Rebuild the state of all live objects from stored messages on start
+    #[clap(long, default_value_t = false)]
+    pub processor_replay_on_start: bool,
+
+    /// Number of threads applying messages of different live objects concurrently, 0 uses one per core
+    #[clap(long, default_value_t = 0)]
+    pub processor_workers: usize,
+
+    /// Number of automatic retries of a message that failed with a transient error
+    #[clap(long, default_value_t = 3)]
+    pub processor_max_retries: u32,
+
+    /// Seconds until the first automatic retry of a failed message, doubled with every retry
+    #[clap(long, default_value_t = 10)]
+    pub processor_retry_backoff: u64,
+}
+
 #[derive(Clone, Debug, Args)]
 pub struct RpcConfigs {
     /// Port for JSON RPC Server
     #[clap(long, default_value_t = 1319)]
     pub json_rpc_port: u16,
+
+    /// Lets RPC callers have messages signed with the node account, anyone reaching the RPC
+    /// port then acts as the node
+    #[clap(long)]
+    pub json_rpc_allow_node_signing: bool,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -89,3 +156,10 @@ pub struct TracingConfigs {
     #[clap(long, default_value = "logs/ramd.log")]
     pub tracing_path: PathBuf,
 }
+
+#[derive(Clone, Debug, Args)]
+pub struct VmConfigs {
+    /// Number of compiled modules kept in memory
+    #[clap(long, default_value_t = 64)]
+    pub vm_module_cache_size: usize,
+}
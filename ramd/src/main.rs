@@ -1,18 +1,21 @@
 use clap::Parser;
 use cli::Subcommand;
-use commands::NodeCmd;
+use commands::{DeadLetterAction, DeadLetterCmd, NodeCmd};
 use dotenv::dotenv;
 use eyre::{eyre, Result};
+use jsonrpsee::http_client::HttpClientBuilder;
 use ramd_config::{
     configs::{
-        network::P2pConfig, node::NodeConfig, rpc::JsonRpcServerConfig, storage::RocksConfig,
-        tracing::TracingConfig,
+        network::P2pConfig, node::NodeConfig, processor::ProcessorConfig, rpc::JsonRpcServerConfig,
+        storage::RocksConfig, tracing::TracingConfig, vm::VmConfig,
     },
     RamdConfig,
 };
 use ramd_db::rocks::RocksStorage;
+use ramd_jsonrpc_api::client::DeadLetterApiClient;
 use ramd_jsonrpc_server::launch;
-use ramd_node::Node;
+use ramd_jsonrpc_types::dead_letter::{PurgeDeadLetters, RetryDeadLetter};
+use ramd_node::{MessageValidator, Node};
 use ramd_p2p_server::Server as P2pServer;
 use ramd_tracing::init as init_tracing;
 use std::{sync::Arc, thread::park};
@@ -51,12 +54,35 @@ async fn main() -> Result<()> {
 
             Ok(())
         }
+        Some(Subcommand::DeadLetter(cmd)) => dead_letter(*cmd).await,
         Some(Subcommand::Relayer(_)) => Err(eyre!("Relayer node not implemented!")),
         // Handled by #[command(arg_required_else_help = true)]
         None => Ok(()),
     }
 }
 
+/// Calls the dead letter API of a running node and prints the result as json
+async fn dead_letter(cmd: DeadLetterCmd) -> eyre::Result<()> {
+    let client = HttpClientBuilder::default().build(&cmd.rpc_url)?;
+
+    let output = match cmd.action {
+        DeadLetterAction::List => serde_json::to_string_pretty(&client.dead_letters().await?)?,
+        DeadLetterAction::Retry { message_id } => serde_json::to_string_pretty(
+            &client
+                .retry_dead_letter(RetryDeadLetter { message_id })
+                .await?,
+        )?,
+        DeadLetterAction::Purge { message_id } => serde_json::to_string_pretty(
+            &client
+                .purge_dead_letters(PurgeDeadLetters { message_id })
+                .await?,
+        )?,
+    };
+
+    println!("{}", output);
+    Ok(())
+}
+
 /// This is a temp solution to properly log received error during start-up process
 async fn start(config: &RamdConfig) -> eyre::Result<()> {
     // Init or read ramd config
@@ -70,13 +96,49 @@ async fn start(config: &RamdConfig) -> eyre::Result<()> {
     // Construct RocksDB
     let rocks = Arc::new(RocksStorage::new(&config.rocks)?);
 
-    // Construct a RAM node
-    let node = Arc::new(Node::new(&config.node, rocks.clone())?);
-
-    // Launch p2p server
-    let (mut p2p, _p2p_msg_sender) = P2pServer::new(&config.p2p, rocks.clone())?;
+    // Launch p2p server, gossip is validated by the node before it is forwarded
+    let (p2p_inbound_sender, p2p_inbound_receiver) = async_channel::unbounded();
+    let (mut p2p, p2p_cmd_sender) = P2pServer::new(
+        &config.p2p,
+        rocks.clone(),
+        p2p_inbound_sender,
+        Box::new(MessageValidator),
+    )?;
     tokio::spawn(async move { p2p.launch().await });
 
+    // Construct a RAM node, broadcasting its messages through the p2p server
+    let node = Arc::new(Node::new(
+        &config.node,
+        &config.processor,
+        &config.vm,
+        rocks.clone(),
+        p2p_cmd_sender,
+    )?);
+
+    // Apply messages received from other replicas
+    let inbound_node = node.clone();
+    tokio::spawn(async move {
+        while let Ok(message) = p2p_inbound_receiver.recv().await {
+            let node = inbound_node.clone();
+
+            // processing blocks until the messages are applied
+            match tokio::task::spawn_blocking(move || node.receive_p2p_message(message)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::warn!(target: "ramd::node", "Failed to handle message from the network. Reason: {}", e);
+                }
+                Err(e) => {
+                    tracing::error!(target: "ramd::node", "Message handling task failed. Reason: {}", e);
+                }
+            }
+        }
+    });
+
+    // Resume replication of the live objects, catching up on messages missed while offline
+    for live_object_id in node.replicated_live_objects()? {
+        node.replicate(live_object_id)?;
+    }
+
     // Launch jsonrpc server
     // TODO: for now we don't care about server, simply start it and forget
     // Revisit once proper server handle handling will be required
@@ -155,24 +217,43 @@ fn parse_flags(mut flags: NodeCmd) -> Result<RamdConfig> {
             root_path: flags.node.ramd_dir_name,
             config_path: flags.node.ramd_config_file,
         },
+        processor: ProcessorConfig {
+            atomic_batches: flags.processor.processor_atomic_batches,
+            pool_max_size: flags.processor.processor_pool_max_size,
+            pool_stale_timeout_secs: flags.processor.processor_pool_stale_timeout,
+            replay_on_start: flags.processor.processor_replay_on_start,
+            workers: flags.processor.processor_workers,
+            max_retries: flags.processor.processor_max_retries,
+            retry_backoff_secs: flags.processor.processor_retry_backoff,
+        },
         rocks: RocksConfig {
             path: flags.db.db_rocks_path,
         },
         json_rpc: JsonRpcServerConfig {
             port: flags.rpc.json_rpc_port,
+            allow_node_signing: flags.rpc.json_rpc_allow_node_signing,
         },
         p2p: P2pConfig {
             boot_nodes: flags.network.network_boot_nodes,
+            compression_threshold: (!flags.network.network_no_compression)
+                .then_some(flags.network.network_compression_threshold),
             config_path: flags.network.network_config_path,
+            encoding: flags.network.network_encoding,
             idle_connection_timeout_secs: flags.network.network_idle_connection_timeout,
+            max_invalid_messages: flags.network.network_max_invalid_messages,
             max_peers_limit: flags.network.network_max_peers_limit,
             network_key: flags.network.network_key,
             port: flags.network.network_port,
+            publish_max_retries: flags.network.network_publish_max_retries,
+            publish_retry_backoff_ms: flags.network.network_publish_retry_backoff,
         },
         tracing: TracingConfig {
             path: flags.tracing.tracing_path,
             max_files: flags.tracing.tracing_max_files,
             max_size_bytes: flags.tracing.tracing_max_size_bytes,
         },
+        vm: VmConfig {
+            module_cache_size: flags.vm.vm_module_cache_size,
+        },
     })
 }